@@ -3,6 +3,11 @@ set -euxo pipefail
 main() {
     cargo check --target $TARGET
 
+    if [ $TARGET = x86_64-unknown-linux-gnu ]; then
+        # the unit tests only run on the host
+        cargo test --target $TARGET --lib
+    fi
+
     if [ $TARGET != x86_64-unknown-linux-gnu ]; then
         # fast check (it compiles)
         cargo check --target $TARGET --examples
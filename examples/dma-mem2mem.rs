@@ -0,0 +1,47 @@
+//! Memory to memory DMA transfers: a block copy and a buffer clear
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_std]
+#![no_main]
+
+extern crate panic_halt;
+
+use cortex_m::{asm, singleton};
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+};
+use cortex_m_rt::entry;
+
+static ZERO: u32 = 0;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut rcc = p.RCC.constrain();
+    let channels = p.DMA1.split(&mut rcc.ahb);
+
+    let dst = singleton!(: [u8; 19] = [0; 19]).unwrap();
+    let ((_, _dst), c1, ()) = channels
+        .1
+        .copy(b"The quick brown fox", dst)
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    asm::bkpt();
+
+    let framebuffer = singleton!(: [u32; 256] = [!0; 256]).unwrap();
+    let ((_, _framebuffer), _c1, ()) = c1
+        .fill(&ZERO, framebuffer)
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
@@ -13,7 +13,7 @@ use nb::block;
 use stm32f103xx_hal::{
     prelude::*,
     device,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use motor_driver::Motor;
 use cortex_m_rt::entry;
@@ -38,10 +38,10 @@ fn main() -> ! {
         p.USART1,
         (tx, rx),
         &mut afio.mapr,
-        115_200.bps(),
+        Config::default().baud_rate(115_200.bps()),
         clocks,
         &mut rcc.apb2,
-    );
+    ).unwrap();
 
     let mut rx = serial.split().1;
 
@@ -18,12 +18,14 @@ fn main() -> ! {
     let p = device::Peripherals::take().unwrap();
 
     let mut rcc = p.RCC.constrain();
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
     let mut gpiob = p.GPIOB.split(&mut rcc.apb2);
     let mut afio = p.AFIO.constrain(&mut rcc.apb2);
 
-    afio.mapr.disable_jtag();
+    let (pa15, _pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
 
-    gpiob.pb4.into_push_pull_output(&mut gpiob.crl).set_low();
+    pa15.into_push_pull_output(&mut gpioa.crh).set_low();
+    pb4.into_push_pull_output(&mut gpiob.crl).set_low();
 
     loop {}
 }
@@ -0,0 +1,40 @@
+//! Drives an 8-bit parallel bus on PB8 - PB15 with single BSRR writes
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    gpio::PortGroup,
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut rcc = p.RCC.constrain();
+    let mut gpiob = p.GPIOB.split(&mut rcc.apb2);
+
+    let mut bus = PortGroup::new((
+        gpiob.pb8.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb9.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb10.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb11.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb12.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb13.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb14.into_push_pull_output(&mut gpiob.crh),
+        gpiob.pb15.into_push_pull_output(&mut gpiob.crh),
+    ));
+
+    let mut byte: u8 = 0;
+    loop {
+        bus.write_masked(u16::from(byte) << 8);
+        byte = byte.wrapping_add(1);
+    }
+}
@@ -0,0 +1,75 @@
+//! Interrupt driven serial echo server with software queues
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use core::fmt::Write;
+
+use nb::block;
+use stm32f103xx_hal::{
+    prelude::*,
+    device::USART1,
+    serial::{
+        buffered::{Buffered, Handler, Queue},
+        Config, Serial,
+    },
+};
+use rtfm::app;
+
+#[app(device = stm32f103xx_hal::device)]
+const APP: () = {
+    static mut RX_QUEUE: Queue<[u8; 64]> = Queue::new([0; 64]);
+    static mut TX_QUEUE: Queue<[u8; 64]> = Queue::new([0; 64]);
+    static mut SERIAL: Buffered<USART1> = ();
+    static mut HANDLER: Handler<USART1> = ();
+
+    #[init(resources = [RX_QUEUE, TX_QUEUE])]
+    fn init() {
+        let mut flash = device.FLASH.constrain();
+        let mut rcc = device.RCC.constrain();
+
+        let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+        let mut afio = device.AFIO.constrain(&mut rcc.apb2);
+
+        let mut gpioa = device.GPIOA.split(&mut rcc.apb2);
+
+        // USART1
+        let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+        let rx = gpioa.pa10;
+
+        let serial = Serial::usart1(
+            device.USART1,
+            (tx, rx),
+            &mut afio.mapr,
+            Config::default().baud_rate(115_200.bps()),
+            clocks,
+            &mut rcc.apb2,
+        ).unwrap();
+
+        let (serial, handler) = serial.buffered(resources.RX_QUEUE, resources.TX_QUEUE);
+
+        SERIAL = serial;
+        HANDLER = handler;
+    }
+
+    #[idle(resources = [SERIAL])]
+    fn idle() -> ! {
+        writeln!(resources.SERIAL, "ready").unwrap();
+
+        loop {
+            if let Ok(byte) = resources.SERIAL.read() {
+                block!(resources.SERIAL.write(byte)).unwrap();
+            }
+        }
+    }
+
+    #[interrupt(resources = [HANDLER])]
+    fn USART1() {
+        resources.HANDLER.on_interrupt();
+    }
+};
@@ -11,14 +11,15 @@ use cortex_m::asm;
 use stm32f103xx_hal::{
     prelude::*,
     dma::{dma1, CircBuffer, Event},
-    serial::Serial,
+    device::USART1,
+    serial::{Config, Rx, Serial},
 };
 use rtfm::app;
 
 #[app(device = stm32f103xx_hal::device)]
 const APP: () = {
     static mut BUFFER: [[u8; 8]; 2] = [[0; 8]; 2];
-    static mut CB: CircBuffer<[u8; 8], dma1::C5> = ();
+    static mut CB: CircBuffer<[u8; 8], dma1::C5, Rx<USART1>> = ();
 
     #[init(resources = [BUFFER])]
     fn init() {
@@ -51,10 +52,10 @@ const APP: () = {
             device.USART1,
             (tx, rx),
             &mut afio.mapr,
-            9_600.bps(),
+            Config::default().baud_rate(9_600.bps()),
             clocks,
             &mut rcc.apb2,
-        );
+        ).unwrap();
 
         let rx = serial.split().1;
 
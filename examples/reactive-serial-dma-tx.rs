@@ -0,0 +1,66 @@
+//! Serial interface DMA TX transfer completed from the DMA interrupt
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m::asm;
+use stm32f103xx_hal::{
+    prelude::*,
+    device::USART1,
+    dma::{dma1, Event, Transfer, R},
+    serial::{Config, Serial, Tx},
+};
+use rtfm::app;
+
+#[app(device = stm32f103xx_hal::device)]
+const APP: () = {
+    static mut TRANSFER: Option<Transfer<R, &'static [u8; 19], dma1::C4, Tx<USART1>>> = None;
+
+    #[init(resources = [TRANSFER])]
+    fn init() {
+        let mut flash = device.FLASH.constrain();
+        let mut rcc = device.RCC.constrain();
+
+        let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+        let mut afio = device.AFIO.constrain(&mut rcc.apb2);
+
+        let mut gpioa = device.GPIOA.split(&mut rcc.apb2);
+
+        let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+        let rx = gpioa.pa10;
+
+        let serial = Serial::usart1(
+            device.USART1,
+            (tx, rx),
+            &mut afio.mapr,
+            Config::default().baud_rate(9_600.bps()),
+            clocks,
+            &mut rcc.apb2,
+        ).unwrap();
+
+        let tx = serial.split().0;
+
+        let mut channels = device.DMA1.split(&mut rcc.ahb);
+        channels.4.listen(Event::TransferComplete);
+        channels.4.listen(Event::TransferError);
+
+        *resources.TRANSFER = Some(tx.write_all(channels.4, b"The quick brown fox"));
+    }
+
+    #[interrupt(resources = [TRANSFER])]
+    fn DMA1_CHANNEL4() {
+        let event = resources.TRANSFER.as_mut().and_then(|transfer| transfer.on_interrupt());
+
+        if event == Some(Event::TransferComplete) {
+            let transfer = resources.TRANSFER.take().unwrap();
+            let (_buffer, _c4, _tx) = transfer.wait().map_err(|(e, ..)| e).unwrap();
+
+            asm::bkpt();
+        }
+    }
+};
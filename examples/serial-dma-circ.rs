@@ -13,7 +13,7 @@ use stm32f103xx_hal::{
     prelude::*,
     device,
     dma::Half,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use cortex_m_rt::entry;
 
@@ -52,10 +52,10 @@ fn main() -> ! {
         p.USART1,
         (tx, rx),
         &mut afio.mapr,
-        9_600.bps(),
+        Config::default().baud_rate(9_600.bps()),
         clocks,
         &mut rcc.apb2,
-    );
+    ).unwrap();
 
     let rx = serial.split().1;
     let buf = singleton!(: [[u8; 8]; 2] = [[0; 8]; 2]).unwrap();
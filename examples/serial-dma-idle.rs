@@ -0,0 +1,81 @@
+//! Serial interface circular DMA RX transfer of variable-length frames ended by an idle line
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_std]
+#![no_main]
+
+extern crate panic_halt;
+
+use cortex_m::{asm, singleton};
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    serial::{Config, Serial},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+    let channels = p.DMA1.split(&mut rcc.ahb);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+    // let mut gpiob = p.GPIOB.split(&mut rcc.apb2);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+
+    // USART1
+    // let tx = gpiob.pb6.into_alternate_push_pull(&mut gpiob.crl);
+    // let rx = gpiob.pb7;
+
+    // USART2
+    // let tx = gpioa.pa2.into_alternate_push_pull(&mut gpioa.crl);
+    // let rx = gpioa.pa3;
+
+    // USART3
+    // let tx = gpiob.pb10.into_alternate_push_pull(&mut gpiob.crh);
+    // let rx = gpiob.pb11;
+
+    let serial = Serial::usart1(
+        p.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        Config::default().baud_rate(9_600.bps()),
+        clocks,
+        &mut rcc.apb2,
+    ).unwrap();
+
+    let rx = serial.split().1;
+    let buf = singleton!(: [[u8; 8]; 2] = [[0; 8]; 2]).unwrap();
+
+    let mut ring = rx.circ_read(channels.5, buf).into_ring_buffer();
+
+    // Receive until the line goes idle
+    let mut frame = [0; 16];
+    let mut received = 0;
+    loop {
+        // Check the flag before reading so that bytes that arrived before the idle line are
+        // always part of this frame
+        let idle = ring.is_idle();
+        received += ring.read(&mut frame[received..]).unwrap();
+        if idle && received > 0 || received == frame.len() {
+            break;
+        }
+    }
+    ring.clear_idle_interrupt();
+
+    asm::bkpt();
+
+    loop {}
+}
@@ -12,7 +12,7 @@ use cortex_m::{asm, singleton};
 use stm32f103xx_hal::{
     prelude::*,
     device,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use cortex_m_rt::entry;
 
@@ -51,17 +51,17 @@ fn main() -> ! {
         p.USART1,
         (tx, rx),
         &mut afio.mapr,
-        115_200.bps(),
+        Config::default().baud_rate(115_200.bps()),
         clocks,
         &mut rcc.apb2,
-    );
+    ).unwrap();
 
     let rx = serial.split().1;
     let buf = singleton!(: [u8; 8] = [0; 8]).unwrap();
 
     let t = rx.read_exact(channels.5, buf);
 
-    while !t.is_done() {
+    while !t.is_done().unwrap() {
         let _slice = t.peek();
 
         asm::bkpt();
@@ -12,7 +12,7 @@ use cortex_m::{asm, singleton};
 use stm32f103xx_hal::{
     prelude::*,
     device,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use cortex_m_rt::entry;
 
@@ -51,15 +51,15 @@ fn main() -> ! {
         p.USART1,
         (tx, rx),
         &mut afio.mapr,
-        9_600.bps(),
+        Config::default().baud_rate(9_600.bps()),
         clocks,
         &mut rcc.apb2,
-    );
+    ).unwrap();
 
     let rx = serial.split().1;
     let buf = singleton!(: [u8; 8] = [0; 8]).unwrap();
 
-    let (_buf, _c, _rx) = rx.read_exact(channels.5, buf).wait();
+    let (_buf, _c, _rx) = rx.read_exact(channels.5, buf).wait().map_err(|(e, ..)| e).unwrap();
 
     asm::bkpt();
 
@@ -12,7 +12,7 @@ use cortex_m::asm;
 use stm32f103xx_hal::{
     prelude::*,
     device,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use cortex_m_rt::entry;
 
@@ -51,22 +51,26 @@ fn main() -> ! {
         p.USART1,
         (tx, rx),
         &mut afio.mapr,
-        9_600.bps(),
+        Config::default().baud_rate(9_600.bps()),
         clocks,
         &mut rcc.apb2,
-    );
+    ).unwrap();
 
     let tx = serial.split().0;
 
-    let (_, c, tx) = tx.write_all(channels.4, b"The quick brown fox").wait();
+    let (_, c, tx) = tx
+        .write_all(channels.4, b"The quick brown fox")
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
 
     asm::bkpt();
 
-    let (_, c, tx) = tx.write_all(c, b" jumps").wait();
+    let (_, c, tx) = tx.write_all(c, b" jumps").wait().map_err(|(e, ..)| e).unwrap();
 
     asm::bkpt();
 
-    tx.write_all(c, b" over the lazy dog.").wait();
+    tx.write_all(c, b" over the lazy dog.").wait().map_err(|(e, ..)| e).unwrap();
 
     asm::bkpt();
 
@@ -0,0 +1,71 @@
+//! Serial interface formatted output
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m::asm;
+
+use core::fmt::Write;
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    serial::{Config, Serial},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+
+    // let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+    let mut gpiob = p.GPIOB.split(&mut rcc.apb2);
+
+    // USART1
+    // let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    // let rx = gpioa.pa10;
+
+    // USART1
+    // let tx = gpiob.pb6.into_alternate_push_pull(&mut gpiob.crl);
+    // let rx = gpiob.pb7;
+
+    // USART2
+    // let tx = gpioa.pa2.into_alternate_push_pull(&mut gpioa.crl);
+    // let rx = gpioa.pa3;
+
+    // USART3
+    let tx = gpiob.pb10.into_alternate_push_pull(&mut gpiob.crh);
+    let rx = gpiob.pb11;
+
+    let serial = Serial::usart3(
+        p.USART3,
+        (tx, rx),
+        &mut afio.mapr,
+        Config::default().baud_rate(9_600.bps()),
+        clocks,
+        &mut rcc.apb1,
+    ).unwrap();
+
+    let mut tx = serial.split().0;
+
+    writeln!(tx, "Hello, {}!", "world").unwrap();
+
+    // send `\r\n` line endings to a terminal
+    tx.bwrite_all_crlf(b"line 1\nline 2\n").unwrap();
+    tx.bflush().unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
@@ -0,0 +1,63 @@
+//! Single-wire half-duplex serial interface: DMA request followed by a DMA reply
+//!
+//! PA9 needs a pull-up to 3.3V, e.g. the one of a smart servo bus
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m::{asm, singleton};
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    serial::{Config, Serial},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+    let channels = p.DMA1.split(&mut rcc.ahb);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+
+    // USART1, TX only
+    let tx = gpioa.pa9.into_alternate_open_drain(&mut gpioa.crh);
+
+    let serial = Serial::usart1(
+        p.USART1,
+        tx,
+        &mut afio.mapr,
+        Config::default().baud_rate(115_200.bps()),
+        clocks,
+        &mut rcc.apb2,
+    ).unwrap();
+
+    let (tx, rx) = serial.split();
+
+    // ping servo #1
+    let (_, _, _tx) = tx
+        .write_all(channels.4, b"\xff\xff\x01\x02\x01\xfb")
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    // the receiver is turned back on once the request is out on the wire
+    let buf = singleton!(: [u8; 6] = [0; 6]).unwrap();
+    let (_reply, _, _rx) = rx.read_exact(channels.5, buf).wait().map_err(|(e, ..)| e).unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
@@ -0,0 +1,67 @@
+//! LIN master: writes a frame and then requests one from a slave
+//!
+//! USART1 must be connected to a LIN transceiver
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m::asm;
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    serial::{
+        lin::{Checksum, Lin},
+        Config, LinBreakDetection, Serial,
+    },
+    time::{self, MonoTimer},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let p = device::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+
+    let serial = Serial::usart1(
+        p.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        Config::default()
+            .baud_rate(19_200.bps())
+            .lin(LinBreakDetection::Bits11),
+        clocks,
+        &mut rcc.apb2,
+    ).unwrap();
+
+    let (tx, rx) = serial.split();
+    // the LIN waits are timed with the cycle counter
+    let timer = MonoTimer::new(cp.DWT, time::enable_trace(cp.DCB), clocks);
+    let mut lin = Lin::new(tx, rx, timer);
+
+    lin.write_frame(0x10, &[0x01, 0x02], Checksum::Enhanced).unwrap();
+
+    let mut response = [0; 4];
+    lin.read_frame(0x11, &mut response, Checksum::Enhanced).unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
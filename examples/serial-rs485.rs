@@ -0,0 +1,69 @@
+//! RS-485 transmission with a driver enable pin, blocking and using DMA
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m::asm;
+
+use stm32f103xx_hal::{
+    prelude::*,
+    delay::Delay,
+    device,
+    serial::{rs485::{NoPin, Rs485}, Config, Serial},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+    let cp = cortex_m::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+    let channels = p.DMA1.split(&mut rcc.ahb);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+
+    // driver enable; /RE is tied to DE on the transceiver
+    let de = gpioa.pa8.into_push_pull_output(&mut gpioa.crh);
+
+    let serial = Serial::usart1(
+        p.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        Config::default().baud_rate(9_600.bps()),
+        clocks,
+        &mut rcc.apb2,
+    ).unwrap();
+
+    let (tx, rx) = serial.split();
+    let delay = Delay::new(cp.SYST, clocks);
+
+    let mut rs485 = Rs485::new(tx, rx, de, NoPin, delay).turnaround_delays(10, 10);
+
+    rs485.bwrite_all(b"The quick brown fox").unwrap();
+
+    asm::bkpt();
+
+    let (_, _c, _rs485) = rs485
+        .write_all(channels.4, b" jumps over the lazy dog.")
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
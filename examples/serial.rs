@@ -16,7 +16,7 @@ use nb::block;
 use stm32f103xx_hal::{
     prelude::*,
     device,
-    serial::Serial,
+    serial::{Config, Serial},
 };
 use cortex_m_rt::entry;
 
@@ -54,10 +54,10 @@ fn main() -> ! {
         p.USART3,
         (tx, rx),
         &mut afio.mapr,
-        9_600.bps(),
+        Config::default().baud_rate(9_600.bps()),
         clocks,
         &mut rcc.apb1,
-    );
+    ).unwrap();
 
     let (mut tx, mut rx) = serial.split();
 
@@ -65,7 +65,7 @@ fn main() -> ! {
 
     block!(tx.write(sent)).ok();
 
-    let received = block!(rx.read()).unwrap();
+    let received: u8 = block!(rx.read()).unwrap();
 
     assert_eq!(received, sent);
 
@@ -0,0 +1,70 @@
+//! SPI full duplex DMA transfer test
+//!
+//! Connect MISO (PA6) to MOSI (PA7) to receive the data that's sent
+
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![no_std]
+#![no_main]
+
+extern crate panic_halt;
+
+use cortex_m::{asm, singleton};
+
+use stm32f103xx_hal::{
+    prelude::*,
+    device,
+    spi::{Mode, Phase, Polarity, Spi},
+};
+use cortex_m_rt::entry;
+
+#[entry]
+fn main() -> ! {
+    let p = device::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    let mut afio = p.AFIO.constrain(&mut rcc.apb2);
+    let channels = p.DMA1.split(&mut rcc.ahb);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.apb2);
+
+    let sck = gpioa.pa5.into_alternate_push_pull(&mut gpioa.crl);
+    let miso = gpioa.pa6;
+    let mosi = gpioa.pa7.into_alternate_push_pull(&mut gpioa.crl);
+
+    let spi = Spi::spi1(
+        p.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        Mode {
+            polarity: Polarity::IdleLow,
+            phase: Phase::CaptureOnFirstTransition,
+        },
+        1.mhz(),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    let (_, rx, (spi, tx)) = spi
+        .write_all((channels.2, channels.3), b"The quick brown fox")
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    asm::bkpt();
+
+    let buf = singleton!(: [u8; 8] = *b"01234567").unwrap();
+    let (_buf, _rx, (_spi, _tx)) = spi
+        .read_write((rx, tx), buf)
+        .wait()
+        .map_err(|(e, ..)| e)
+        .unwrap();
+
+    asm::bkpt();
+
+    loop {}
+}
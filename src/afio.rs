@@ -1,5 +1,18 @@
 use crate::device::{afio, AFIO};
 
+use crate::gpio::gpioa::{
+    PA0, PA1, PA10, PA11, PA12, PA13, PA14, PA15, PA2, PA3, PA4, PA5, PA6, PA7, PA8, PA9,
+};
+use crate::gpio::gpiob::{
+    PB0, PB1, PB10, PB11, PB12, PB13, PB14, PB15, PB2, PB3, PB4, PB5, PB6, PB7, PB8, PB9,
+};
+use crate::gpio::gpioc::{
+    PC0, PC1, PC10, PC11, PC12, PC13, PC14, PC15, PC2, PC3, PC4, PC5, PC6, PC7, PC8, PC9,
+};
+use crate::gpio::gpiod::{
+    PD0, PD1, PD10, PD11, PD12, PD13, PD14, PD15, PD2, PD3, PD4, PD5, PD6, PD7, PD8, PD9,
+};
+use crate::gpio::{Alternate, Debugger, Floating, Input, PushPull};
 use crate::rcc::APB2;
 
 pub trait AfioExt {
@@ -14,7 +27,9 @@ impl AfioExt for AFIO {
 
         Parts {
             evcr: EVCR { _0: () },
-            mapr: MAPR { _0: () },
+            mapr: MAPR {
+                swj_cfg: SwjCfg::Full,
+            },
             exticr1:  EXTICR1 { _0: () },
             exticr2:  EXTICR2 { _0: () },
             exticr3:  EXTICR3 { _0: () },
@@ -42,20 +57,280 @@ impl EVCR {
     pub fn evcr(&mut self) -> &afio::EVCR {
         unsafe { &(*AFIO::ptr()).evcr }
     }
+
+    /// Routes the Cortex-M3 EVENTOUT signal to `pin`
+    ///
+    /// The output starts disabled; call `EventOut::enable` to start pulsing the pin every time the
+    /// core executes a `SEV` instruction.
+    pub fn eventout<PIN>(mut self, pin: PIN) -> EventOut<PIN>
+    where
+        PIN: EventOutPin,
+    {
+        self.evcr().write(|w| unsafe {
+            w.port()
+                .bits(PIN::PORT)
+                .pin()
+                .bits(PIN::PIN)
+                .evoe()
+                .clear_bit()
+        });
+
+        EventOut { evcr: self, pin }
+    }
+}
+
+/// Pin that can output the Cortex-M3 EVENTOUT signal
+pub trait EventOutPin {
+    /// Value of the EVCR.PORT field
+    const PORT: u8;
+    /// Value of the EVCR.PIN field
+    const PIN: u8;
+}
+
+macro_rules! eventout_pins {
+    ($($PXi:ident: ($port:expr, $pin:expr),)+) => {
+        $(
+            impl EventOutPin for $PXi<Alternate<PushPull>> {
+                const PORT: u8 = $port;
+                const PIN: u8 = $pin;
+            }
+        )+
+    }
+}
+
+eventout_pins! {
+    PA0: (0b000, 0),
+    PA1: (0b000, 1),
+    PA2: (0b000, 2),
+    PA3: (0b000, 3),
+    PA4: (0b000, 4),
+    PA5: (0b000, 5),
+    PA6: (0b000, 6),
+    PA7: (0b000, 7),
+    PA8: (0b000, 8),
+    PA9: (0b000, 9),
+    PA10: (0b000, 10),
+    PA11: (0b000, 11),
+    PA12: (0b000, 12),
+    PA13: (0b000, 13),
+    PA14: (0b000, 14),
+    PA15: (0b000, 15),
+    PB0: (0b001, 0),
+    PB1: (0b001, 1),
+    PB2: (0b001, 2),
+    PB3: (0b001, 3),
+    PB4: (0b001, 4),
+    PB5: (0b001, 5),
+    PB6: (0b001, 6),
+    PB7: (0b001, 7),
+    PB8: (0b001, 8),
+    PB9: (0b001, 9),
+    PB10: (0b001, 10),
+    PB11: (0b001, 11),
+    PB12: (0b001, 12),
+    PB13: (0b001, 13),
+    PB14: (0b001, 14),
+    PB15: (0b001, 15),
+    PC0: (0b010, 0),
+    PC1: (0b010, 1),
+    PC2: (0b010, 2),
+    PC3: (0b010, 3),
+    PC4: (0b010, 4),
+    PC5: (0b010, 5),
+    PC6: (0b010, 6),
+    PC7: (0b010, 7),
+    PC8: (0b010, 8),
+    PC9: (0b010, 9),
+    PC10: (0b010, 10),
+    PC11: (0b010, 11),
+    PC12: (0b010, 12),
+    PC13: (0b010, 13),
+    PC14: (0b010, 14),
+    PC15: (0b010, 15),
+    PD0: (0b011, 0),
+    PD1: (0b011, 1),
+    PD2: (0b011, 2),
+    PD3: (0b011, 3),
+    PD4: (0b011, 4),
+    PD5: (0b011, 5),
+    PD6: (0b011, 6),
+    PD7: (0b011, 7),
+    PD8: (0b011, 8),
+    PD9: (0b011, 9),
+    PD10: (0b011, 10),
+    PD11: (0b011, 11),
+    PD12: (0b011, 12),
+    PD13: (0b011, 13),
+    PD14: (0b011, 14),
+    PD15: (0b011, 15),
+}
+
+/// Cortex-M3 EVENTOUT signal routed to a pin
+pub struct EventOut<PIN> {
+    evcr: EVCR,
+    pin: PIN,
+}
+
+impl<PIN> EventOut<PIN> {
+    /// Enables the event output
+    pub fn enable(&mut self) {
+        self.evcr.evcr().modify(|_, w| w.evoe().set_bit());
+    }
+
+    /// Disables the event output
+    pub fn disable(&mut self) {
+        self.evcr.evcr().modify(|_, w| w.evoe().clear_bit());
+    }
+
+    /// Returns `true` if the event output is enabled
+    pub fn is_enabled(&mut self) -> bool {
+        self.evcr.evcr().read().evoe().bit_is_set()
+    }
+
+    /// Disables the event output and releases the EVCR register and the pin
+    pub fn release(mut self) -> (EVCR, PIN) {
+        self.disable();
+
+        (self.evcr, self.pin)
+    }
+}
+
+/// Serial wire JTAG configuration (SWJ_CFG)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SwjCfg {
+    /// Full SWJ (JTAG-DP + SW-DP); reset state
+    Full,
+    /// Full SWJ (JTAG-DP + SW-DP) but without NJTRST; frees PB4
+    FullWithoutNjtrst,
+    /// JTAG-DP disabled and SW-DP enabled; frees PA15, PB3 and PB4
+    JtagDisabled,
+    /// JTAG-DP and SW-DP disabled; frees PA13, PA14, PA15, PB3 and PB4
+    Disabled,
+}
+
+impl SwjCfg {
+    fn bits(self) -> u8 {
+        match self {
+            SwjCfg::Full => 0b000,
+            SwjCfg::FullWithoutNjtrst => 0b001,
+            SwjCfg::JtagDisabled => 0b010,
+            SwjCfg::Disabled => 0b100,
+        }
+    }
 }
 
 pub struct MAPR {
-    _0: (),
+    swj_cfg: SwjCfg,
+}
+
+/// PB4, either still used as NJTRST or already released by `MAPR::release_njtrst`
+pub trait Pb4 {
+    #[doc(hidden)]
+    fn release(self) -> PB4<Input<Floating>>;
+}
+
+impl Pb4 for PB4<Debugger> {
+    fn release(self) -> PB4<Input<Floating>> {
+        self.activate()
+    }
+}
+
+impl Pb4 for PB4<Input<Floating>> {
+    fn release(self) -> PB4<Input<Floating>> {
+        self
+    }
 }
 
 impl MAPR {
-    pub fn mapr(&mut self) -> &afio::MAPR {
+    /// Use `modify_mapr` to write the register: a plain read-modify-write clobbers SWJ_CFG
+    pub(crate) fn mapr(&mut self) -> &afio::MAPR {
         unsafe { &(*AFIO::ptr()).mapr }
     }
 
-    /// Disables the JTAG to free up pb3, pb4 and pa15 for normal use
-    pub fn disable_jtag(&mut self) {
-        self.mapr().modify(|_, w| unsafe{w.swj_cfg().bits(0b010)})
+    /// Modifies the MAPR register preserving the SWJ configuration
+    ///
+    /// The SWJ_CFG bits are write-only and read back as an undefined value so a plain
+    /// read-modify-write of MAPR could re-enable the debug port. Use this to set the remaps that
+    /// the peripheral constructors don't cover, e.g. CAN_REMAP or PD01_REMAP.
+    pub fn modify_mapr<F>(&mut self, mod_fn: F)
+    where
+        F: for<'w> FnOnce(&afio::mapr::R, &'w mut afio::mapr::W) -> &'w mut afio::mapr::W,
+    {
+        let swj_cfg = self.swj_cfg.bits();
+        self.mapr()
+            .modify(|r, w| unsafe { mod_fn(r, w).swj_cfg().bits(swj_cfg) });
+    }
+
+    /// Returns the current serial wire JTAG configuration
+    pub fn swj_cfg(&self) -> SwjCfg {
+        self.swj_cfg
+    }
+
+    /// Disables NJTRST to free up pb4 for normal use; JTAG and SWD remain usable
+    pub fn release_njtrst(&mut self, pb4: PB4<Debugger>) -> PB4<Input<Floating>> {
+        self.set_swj_cfg(SwjCfg::FullWithoutNjtrst);
+
+        pb4.activate()
+    }
+
+    /// Disables the JTAG to free up pb3, pb4 and pa15 for normal use; SWD remains usable
+    ///
+    /// `pb4` may already have been released by `release_njtrst`.
+    pub fn disable_jtag<PIN>(
+        &mut self,
+        pa15: PA15<Debugger>,
+        pb3: PB3<Debugger>,
+        pb4: PIN,
+    ) -> (
+        PA15<Input<Floating>>,
+        PB3<Input<Floating>>,
+        PB4<Input<Floating>>,
+    )
+    where
+        PIN: Pb4,
+    {
+        self.set_swj_cfg(SwjCfg::JtagDisabled);
+
+        (pa15.activate(), pb3.activate(), pb4.release())
+    }
+
+    /// Disables both JTAG and SWD to free up all the debug pins for normal use
+    ///
+    /// NOTE after this the device can only be reprogrammed by holding it in reset while connecting
+    /// the debugger, or through the bootloader
+    ///
+    /// `pb4` may already have been released by `release_njtrst`.
+    pub fn disable_swj<PIN>(
+        &mut self,
+        pa13: PA13<Debugger>,
+        pa14: PA14<Debugger>,
+        pa15: PA15<Debugger>,
+        pb3: PB3<Debugger>,
+        pb4: PIN,
+    ) -> (
+        PA13<Input<Floating>>,
+        PA14<Input<Floating>>,
+        PA15<Input<Floating>>,
+        PB3<Input<Floating>>,
+        PB4<Input<Floating>>,
+    )
+    where
+        PIN: Pb4,
+    {
+        self.set_swj_cfg(SwjCfg::Disabled);
+
+        (
+            pa13.activate(),
+            pa14.activate(),
+            pa15.activate(),
+            pb3.activate(),
+            pb4.release(),
+        )
+    }
+
+    fn set_swj_cfg(&mut self, swj_cfg: SwjCfg) {
+        self.swj_cfg = swj_cfg;
+        self.modify_mapr(|_, w| w);
     }
 }
 
@@ -25,6 +25,30 @@ impl Delay {
     pub fn free(self) -> SYST {
         self.syst
     }
+
+    fn delay_ticks(&mut self, mut total_rvr: u64) {
+        // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
+        const MAX_RVR: u32 =  0x00FF_FFFF;
+
+        while total_rvr != 0 {
+            let current_rvr = if total_rvr <= u64::from(MAX_RVR) {
+                total_rvr as u32
+            } else {
+                MAX_RVR
+            };
+
+            self.syst.set_reload(current_rvr);
+            self.syst.clear_current();
+            self.syst.enable_counter();
+
+            // Update the tracking variable while we are waiting...
+            total_rvr -= u64::from(current_rvr);
+
+            while !self.syst.has_wrapped() {}
+
+            self.syst.disable_counter();
+        }
+    }
 }
 
 impl DelayMs<u32> for Delay {
@@ -47,29 +71,9 @@ impl DelayMs<u8> for Delay {
 
 impl DelayUs<u32> for Delay {
     fn delay_us(&mut self, us: u32) {
-        // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
-        const MAX_RVR: u32 =  0x00FF_FFFF;
+        let total_rvr = u64::from(us) * u64::from(self.clocks.sysclk().0 / 1_000_000);
 
-        let mut total_rvr = us * (self.clocks.sysclk().0 / 1_000_000);
-
-        while total_rvr != 0 {
-            let current_rvr = if total_rvr <= MAX_RVR {
-                total_rvr
-            } else {
-                MAX_RVR
-            };
-
-            self.syst.set_reload(current_rvr);
-            self.syst.clear_current();
-            self.syst.enable_counter();
-
-            // Update the tracking variable while we are waiting...
-            total_rvr -= current_rvr;
-
-            while !self.syst.has_wrapped() {}
-
-            self.syst.disable_counter();
-        }
+        self.delay_ticks(total_rvr);
     }
 }
 
@@ -84,3 +88,24 @@ impl DelayUs<u8> for Delay {
         self.delay_us(u32(us))
     }
 }
+
+#[cfg(feature = "embedded-hal-1")]
+impl crate::hal1::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        // round up: the delay must never be shorter than requested
+        let total_rvr =
+            (u64::from(ns) * u64::from(self.clocks.sysclk().0) + 999_999_999) / 1_000_000_000;
+
+        self.delay_ticks(total_rvr);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        DelayUs::<u32>::delay_us(self, us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        for _ in 0..ms {
+            DelayUs::<u32>::delay_us(self, 1_000);
+        }
+    }
+}
@@ -1,20 +1,51 @@
 #![allow(dead_code)]
 
 use core::marker::PhantomData;
-use core::ops;
+use core::mem::{self, ManuallyDrop};
+use core::sync::atomic::{self, Ordering};
+use core::{ops, ptr};
+
+pub use embedded_dma::{ReadBuffer, WriteBuffer};
 
 use crate::rcc::AHB;
 
 #[derive(Debug)]
 pub enum Error {
     Overrun,
+    /// A circular transmission sent a half of the buffer before it was refilled
+    Underrun,
+    /// The DMA accessed a reserved address; the channel has been disabled by hardware
+    TransferError,
     #[doc(hidden)]
     _Extensible,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Event {
     HalfTransfer,
     TransferComplete,
+    /// Only reported by `on_interrupt`; listening for it enables the transfer error interrupt
+    TransferError,
+}
+
+/// Channel priority level, used to arbitrate between the channels of a DMA controller
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    VeryHigh,
+}
+
+impl Priority {
+    fn bits(self) -> u8 {
+        match self {
+            Priority::Low => 0b00,
+            Priority::Medium => 0b01,
+            Priority::High => 0b10,
+            Priority::VeryHigh => 0b11,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -23,12 +54,13 @@ pub enum Half {
     Second,
 }
 
-pub struct CircBuffer<BUFFER, CHANNEL>
+pub struct CircBuffer<BUFFER, CHANNEL, PAYLOAD = ()>
 where
     BUFFER: 'static,
 {
     buffer: &'static mut [BUFFER; 2],
     channel: CHANNEL,
+    payload: PAYLOAD,
     readable_half: Half,
 }
 
@@ -37,63 +69,339 @@ impl<BUFFER, CHANNEL> CircBuffer<BUFFER, CHANNEL> {
         CircBuffer {
             buffer: buf,
             channel: chan,
+            payload: (),
             readable_half: Half::Second,
         }
     }
+
+    /// Attaches the peripheral that issues the DMA requests
+    pub(crate) fn with_payload<PAYLOAD>(
+        self,
+        payload: PAYLOAD,
+    ) -> CircBuffer<BUFFER, CHANNEL, PAYLOAD> {
+        CircBuffer {
+            buffer: self.buffer,
+            channel: self.channel,
+            payload,
+            readable_half: self.readable_half,
+        }
+    }
+}
+
+impl<BUFFER, CHANNEL, PAYLOAD> CircBuffer<BUFFER, CHANNEL, PAYLOAD> {
+    pub(crate) fn payload(&self) -> &PAYLOAD {
+        &self.payload
+    }
+
+    pub(crate) fn payload_mut(&mut self) -> &mut PAYLOAD {
+        &mut self.payload
+    }
 }
 
-pub trait Static<B> {
-    fn borrow(&self) -> &B;
+/// Circular transmission out of the two halves of a buffer; the counterpart of `CircBuffer`
+///
+/// While the DMA sends one half, the application refills the other one. If the application
+/// falls behind, the DMA keeps sending the stale contents and `Error::Underrun` is reported.
+pub struct CircWriteBuffer<BUFFER, CHANNEL, PAYLOAD = ()>
+where
+    BUFFER: 'static,
+{
+    buffer: &'static mut [BUFFER; 2],
+    channel: CHANNEL,
+    payload: PAYLOAD,
+    // half the DMA finished sending most recently
+    last_freed_half: Half,
+    // half that has been sent but not refilled yet
+    writable: Option<Half>,
 }
 
-impl<B> Static<B> for &'static B {
-    fn borrow(&self) -> &B {
-        *self
+impl<BUFFER, CHANNEL> CircWriteBuffer<BUFFER, CHANNEL> {
+    pub(crate) fn new(buf: &'static mut [BUFFER; 2], chan: CHANNEL) -> Self {
+        CircWriteBuffer {
+            buffer: buf,
+            channel: chan,
+            payload: (),
+            last_freed_half: Half::Second,
+            writable: None,
+        }
+    }
+
+    /// Attaches the peripheral that issues the DMA requests
+    pub(crate) fn with_payload<PAYLOAD>(
+        self,
+        payload: PAYLOAD,
+    ) -> CircWriteBuffer<BUFFER, CHANNEL, PAYLOAD> {
+        CircWriteBuffer {
+            buffer: self.buffer,
+            channel: self.channel,
+            payload,
+            last_freed_half: self.last_freed_half,
+            writable: self.writable,
+        }
     }
 }
 
-impl<B> Static<B> for &'static mut B {
-    fn borrow(&self) -> &B {
-        *self
+/// Byte-granular view of a circular DMA reception
+///
+/// Unlike `CircBuffer`, which hands out a half of the buffer once the DMA has filled it, this
+/// reader uses the channel's transfer counter (CNDTR) to return whatever has been received so far.
+/// This keeps latency low for short, variable-length messages, e.g. when the end of a message is
+/// signaled by a USART idle line.
+///
+/// The DMA's progress is tracked with the channel's half transfer and transfer complete flags,
+/// which the ring buffer consumes: nothing else may clear them, e.g. an interrupt handler that
+/// stole the channel. It has to be polled at least once every half buffer; if both flags are found
+/// set the number of laps is unknown and `Error::Overrun` is returned.
+pub struct RingBuffer<BUFFER, CHANNEL, PAYLOAD = ()>
+where
+    BUFFER: 'static,
+{
+    buffer: &'static mut [BUFFER; 2],
+    channel: CHANNEL,
+    payload: PAYLOAD,
+    // index of the next byte to read
+    read: usize,
+    // number of halves the DMA finished since the reader last wrapped around
+    halves: usize,
+}
+
+impl<BUFFER, CHANNEL, PAYLOAD> CircBuffer<BUFFER, CHANNEL, PAYLOAD> {
+    /// Turns this circular buffer into a byte-granular `RingBuffer`
+    ///
+    /// The reader starts at the beginning of the buffer, so this should be called before any half
+    /// has been consumed with `readable_half` / `peek`.
+    pub fn into_ring_buffer(self) -> RingBuffer<BUFFER, CHANNEL, PAYLOAD> {
+        RingBuffer {
+            buffer: self.buffer,
+            channel: self.channel,
+            payload: self.payload,
+            read: 0,
+            halves: 0,
+        }
     }
 }
 
+impl<BUFFER, CHANNEL, PAYLOAD> RingBuffer<BUFFER, CHANNEL, PAYLOAD> {
+    pub(crate) fn payload(&self) -> &PAYLOAD {
+        &self.payload
+    }
+
+    pub(crate) fn payload_mut(&mut self) -> &mut PAYLOAD {
+        &mut self.payload
+    }
+}
+
+/// Buffer lent to the DMA for the duration of a `scope`
+///
+/// Implements `ReadBuffer` and `WriteBuffer` so it can be passed to any DMA API in place of a
+/// `&'static mut` buffer.
+pub struct Scoped<'s, T> {
+    ptr: *mut T,
+    len: usize,
+    _borrow: PhantomData<&'s mut [T]>,
+}
+
+unsafe impl<'s, T> ReadBuffer for Scoped<'s, T>
+where
+    T: embedded_dma::Word,
+{
+    type Word = T;
+
+    unsafe fn read_buffer(&self) -> (*const T, usize) {
+        (self.ptr, self.len)
+    }
+}
+
+unsafe impl<'s, T> WriteBuffer for Scoped<'s, T>
+where
+    T: embedded_dma::Word,
+{
+    type Word = T;
+
+    unsafe fn write_buffer(&mut self) -> (*mut T, usize) {
+        (self.ptr, self.len)
+    }
+}
+
+/// Lends a non-`'static` (e.g. stack allocated) `buffer` to the DMA transfers started in `f`
+///
+/// When `f` returns (or unwinds) every DMA channel still accessing `buffer` is disabled, so the
+/// transfers should be waited for inside `f`. This also covers transfers that were leaked with
+/// `mem::forget`; the `Scoped` buffer itself can't escape `f`.
+///
+/// ``` ignore
+/// let mut buffer = [0; 16];
+/// let (c5, rx) = dma::scope(&mut buffer, |buffer| {
+///     let (_, c5, rx) = rx.read_exact(c5, buffer).wait().map_err(|(e, ..)| e).unwrap();
+///     (c5, rx)
+/// });
+/// ```
+pub fn scope<'a, T, R, F>(buffer: &'a mut [T], f: F) -> R
+where
+    F: for<'s> FnOnce(Scoped<'s, T>) -> R,
+{
+    struct Guard {
+        start: u32,
+        end: u32,
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            dma1::abort_transfers(self.start, self.end);
+            dma2::abort_transfers(self.start, self.end);
+
+            // NOTE(compiler_fence) operations on the buffer should not be reordered before the
+            // previous statements, which stop the DMA transfers
+            atomic::compiler_fence(Ordering::SeqCst);
+        }
+    }
+
+    let start = buffer.as_mut_ptr() as usize as u32;
+    let _guard = Guard {
+        start,
+        end: start + (buffer.len() * mem::size_of::<T>()) as u32,
+    };
+
+    f(Scoped {
+        ptr: buffer.as_mut_ptr(),
+        len: buffer.len(),
+        _borrow: PhantomData,
+    })
+}
+
+/// Peripheral side of a DMA transfer
+pub trait TransferPayload {
+    /// Stops the peripheral from issuing further DMA requests
+    fn stop(&mut self);
+}
+
+impl TransferPayload for () {
+    fn stop(&mut self) {}
+}
+
+/// Element that the DMA can move
+pub trait Word {
+    /// Value of the MSIZE / PSIZE fields for this element
+    const SIZE: u8;
+}
+
+impl Word for u8 {
+    const SIZE: u8 = 0b00;
+}
+
+impl Word for u16 {
+    const SIZE: u8 = 0b01;
+}
+
+impl Word for u32 {
+    const SIZE: u8 = 0b10;
+}
+
+/// A DMA channel
+pub trait Channel {
+    /// Disables the channel, stopping any transfer in progress, and clears its flags
+    fn stop(&mut self);
+}
+
 pub trait DmaExt {
     type Channels;
 
     fn split(self, ahb: &mut AHB) -> Self::Channels;
 }
 
-pub struct Transfer<MODE, BUFFER, CHANNEL, PAYLOAD> {
+/// DMA transfer; the buffer is owned by the transfer until it's done
+///
+/// Dropping a transfer that is still in progress stops it: the channel is disabled and the payload
+/// is told to stop issuing DMA requests, as `stop` does.
+pub struct Transfer<MODE, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+{
     _mode: PhantomData<MODE>,
     buffer: BUFFER,
     channel: CHANNEL,
     payload: PAYLOAD,
+    // number of items programmed in CNDTR
+    len: usize,
+    // `TransferPayload::stop` of the payload; `Drop` can't require the trait
+    stop_payload: fn(&mut PAYLOAD),
 }
 
-impl<BUFFER, CHANNEL, PAYLOAD> Transfer<R, BUFFER, CHANNEL, PAYLOAD> {
-    pub(crate) fn r(buffer: BUFFER, channel: CHANNEL, payload: PAYLOAD) -> Self {
+impl<BUFFER, CHANNEL, PAYLOAD> Transfer<R, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+    PAYLOAD: TransferPayload,
+{
+    pub(crate) fn r(buffer: BUFFER, channel: CHANNEL, payload: PAYLOAD, len: usize) -> Self {
         Transfer {
             _mode: PhantomData,
             buffer,
             channel,
             payload,
+            len,
+            stop_payload: PAYLOAD::stop,
         }
     }
 }
 
-impl<BUFFER, CHANNEL, PAYLOAD> Transfer<W, BUFFER, CHANNEL, PAYLOAD> {
-    pub(crate) fn w(buffer: BUFFER, channel: CHANNEL, payload: PAYLOAD) -> Self {
+impl<BUFFER, CHANNEL, PAYLOAD> Transfer<W, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+    PAYLOAD: TransferPayload,
+{
+    pub(crate) fn w(buffer: BUFFER, channel: CHANNEL, payload: PAYLOAD, len: usize) -> Self {
         Transfer {
             _mode: PhantomData,
             buffer,
             channel,
             payload,
+            len,
+            stop_payload: PAYLOAD::stop,
+        }
+    }
+}
+
+impl<MODE, BUFFER, CHANNEL, PAYLOAD> Transfer<MODE, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+{
+    pub(crate) fn payload(&self) -> &PAYLOAD {
+        &self.payload
+    }
+
+    /// Takes the transfer apart without running its destructor
+    fn into_parts(self) -> (BUFFER, CHANNEL, PAYLOAD) {
+        let this = ManuallyDrop::new(self);
+
+        // NOTE(unsafe) each field is read exactly once and `this` is never dropped
+        unsafe {
+            (
+                ptr::read(&this.buffer),
+                ptr::read(&this.channel),
+                ptr::read(&this.payload),
+            )
         }
     }
 }
 
-impl<BUFFER, CHANNEL, PAYLOAD> ops::Deref for Transfer<R, BUFFER, CHANNEL, PAYLOAD> {
+impl<MODE, BUFFER, CHANNEL, PAYLOAD> Drop for Transfer<MODE, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+{
+    fn drop(&mut self) {
+        self.channel.stop();
+        (self.stop_payload)(&mut self.payload);
+
+        // NOTE(compiler_fence) dropping the buffer should not be reordered before the previous
+        // statements, which stop the DMA transfer
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<BUFFER, CHANNEL, PAYLOAD> ops::Deref for Transfer<R, BUFFER, CHANNEL, PAYLOAD>
+where
+    CHANNEL: Channel,
+{
     type Target = BUFFER;
 
     fn deref(&self) -> &BUFFER {
@@ -110,6 +418,7 @@ pub struct W;
 macro_rules! dma {
     ($($DMAX:ident: ($dmaX:ident, $dmaXen:ident, $dmaXrst:ident, {
         $($CX:ident: (
+            $INTX:ident,
             $ccrX:ident,
             $CCRX:ident,
             $cndtrX:ident,
@@ -118,20 +427,30 @@ macro_rules! dma {
             $CPARX:ident,
             $cmarX:ident,
             $CMARX:ident,
+            $gifX:ident,
             $htifX:ident,
             $tcifX:ident,
+            $teifX:ident,
             $chtifX:ident,
             $ctcifX:ident,
+            $cteifX:ident,
             $cgifX:ident
         ),)+
     }),)+) => {
         $(
             pub mod $dmaX {
+                use core::cmp;
                 use core::sync::atomic::{self, Ordering};
 
-                use crate::device::{$DMAX, dma1};
+                use crate::device::{$DMAX, dma1, Interrupt, RCC};
 
-                use crate::dma::{CircBuffer, DmaExt, Error, Event, Half, Transfer, W};
+                use cast::u16;
+
+                use crate::dma::{
+                    Channel, CircBuffer, CircWriteBuffer, DmaChannel, DmaExt, Error, Event, Half, Priority,
+                    ReadBuffer, Request, RingBuffer, Target, Transfer, TransferPayload, W, Word,
+                    WriteBuffer,
+                };
                 use crate::rcc::AHB;
 
                 pub struct Channels((), $(pub $CX),+);
@@ -140,12 +459,26 @@ macro_rules! dma {
                     pub struct $CX { _0: () }
 
                     impl $CX {
+                        /// Interrupt line of this channel
+                        ///
+                        /// NOTE: channels 4 and 5 of DMA2 share a single interrupt line; the
+                        /// handler has to use `is_pending` to find out which channel fired
+                        pub const INTERRUPT: Interrupt = Interrupt::$INTX;
+
+                        /// Returns `true` if any of the flags of this channel is set
+                        pub fn is_pending(&self) -> bool {
+                            self.isr().$gifX().bit_is_set()
+                        }
+
                         pub fn listen(&mut self, event: Event) {
                             match event {
                                 Event::HalfTransfer => self.ccr().modify(|_, w| w.htie().set_bit()),
                                 Event::TransferComplete => {
                                     self.ccr().modify(|_, w| w.tcie().set_bit())
                                 }
+                                Event::TransferError => {
+                                    self.ccr().modify(|_, w| w.teie().set_bit())
+                                }
                             }
                         }
 
@@ -157,9 +490,58 @@ macro_rules! dma {
                                 Event::TransferComplete => {
                                     self.ccr().modify(|_, w| w.tcie().clear_bit())
                                 }
+                                Event::TransferError => {
+                                    self.ccr().modify(|_, w| w.teie().clear_bit())
+                                }
                             }
                         }
 
+                        /// Clears the pending flags of this channel and returns the event that
+                        /// raised the interrupt
+                        ///
+                        /// Meant to be called from the channel's interrupt handler. If several
+                        /// flags are pending the most relevant event is returned: a transfer
+                        /// error, then a transfer complete, then a half transfer. `None` means
+                        /// that this channel has nothing pending, e.g. because the interrupt
+                        /// was raised by the other channel of DMA2's shared 4/5 line.
+                        pub fn on_interrupt(&mut self) -> Option<Event> {
+                            let isr = self.isr();
+                            let half = isr.$htifX().bit_is_set();
+                            let complete = isr.$tcifX().bit_is_set();
+                            let error = isr.$teifX().bit_is_set();
+
+                            // only clear the flags that were read, to not lose a newer event
+                            self.ifcr().write(|w| {
+                                w.$chtifX()
+                                    .bit(half)
+                                    .$ctcifX()
+                                    .bit(complete)
+                                    .$cteifX()
+                                    .bit(error)
+                            });
+
+                            if error {
+                                Some(Event::TransferError)
+                            } else if complete {
+                                Some(Event::TransferComplete)
+                            } else if half {
+                                Some(Event::HalfTransfer)
+                            } else {
+                                None
+                            }
+                        }
+
+                        fn is_enabled(&self) -> bool {
+                            // NOTE(unsafe) atomic read with no side effects
+                            unsafe { (*$DMAX::ptr()).$ccrX.read().en().bit_is_set() }
+                        }
+
+                        /// Sets the priority of the channel; used by the transfers started
+                        /// afterwards
+                        pub fn set_priority(&mut self, priority: Priority) {
+                            self.ccr().modify(|_, w| unsafe { w.pl().bits(priority.bits()) });
+                        }
+
                         pub(crate) fn isr(&self) -> dma1::isr::R {
                             // NOTE(unsafe) atomic read with no side effects
                             unsafe { (*$DMAX::ptr()).isr.read() }
@@ -190,9 +572,373 @@ macro_rules! dma {
                             unsafe { (*$DMAX::ptr()).$cndtrX.read().bits() }
                         }
 
+                        /// Copies the elements of `src` into the start of `dst` (memory to memory)
+                        ///
+                        /// # Panics
+                        ///
+                        /// If `dst` is shorter than `src`
+                        pub fn copy<S, D, T>(
+                            mut self,
+                            src: S,
+                            mut dst: D,
+                        ) -> Transfer<W, (S, D), $CX, ()>
+                        where
+                            S: ReadBuffer<Word = T>,
+                            D: WriteBuffer<Word = T>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) the buffers are owned by the transfer until it's done
+                            let (src_address, len) = unsafe { src.read_buffer() };
+                            let (dst_address, dst_len) = unsafe { dst.write_buffer() };
+                            assert!(dst_len >= len);
+
+                            self.mem2mem::<T>(
+                                src_address as usize as u32,
+                                true,
+                                dst_address as usize as u32,
+                                len,
+                            );
+
+                            Transfer::w((src, dst), self, (), len)
+                        }
+
+                        /// Sets every element of `dst` to `*value` (memory to memory)
+                        pub fn fill<D, T>(
+                            mut self,
+                            value: &'static T,
+                            mut dst: D,
+                        ) -> Transfer<W, (&'static T, D), $CX, ()>
+                        where
+                            D: WriteBuffer<Word = T>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                            let (dst_address, len) = unsafe { dst.write_buffer() };
+
+                            self.mem2mem::<T>(
+                                value as *const T as usize as u32,
+                                false,
+                                dst_address as usize as u32,
+                                len,
+                            );
+
+                            Transfer::w((value, dst), self, (), len)
+                        }
+
+                        /// Starts filling `buffer` with reads of the peripheral register at
+                        /// `address`
+                        ///
+                        /// MSIZE and PSIZE are selected from the element type `T`.
+                        ///
+                        /// # Safety
+                        ///
+                        /// `address` must be the data register of a peripheral whose DMA request
+                        /// is routed to this channel, and it must be at least as wide as `T`.
+                        pub unsafe fn read_from<B, T>(
+                            mut self,
+                            address: u32,
+                            mut buffer: B,
+                        ) -> Transfer<W, B, $CX, ()>
+                        where
+                            B: WriteBuffer<Word = T>,
+                            T: Word,
+                        {
+                            let (ptr, len) = buffer.write_buffer();
+                            self.start::<T>(address, ptr as usize as u32, len, false, true, false);
+
+                            Transfer::w(buffer, self, (), len)
+                        }
+
+                        /// Starts writing the elements of `buffer` to the peripheral register at
+                        /// `address`
+                        ///
+                        /// MSIZE and PSIZE are selected from the element type `T`.
+                        ///
+                        /// # Safety
+                        ///
+                        /// `address` must be the data register of a peripheral whose DMA request
+                        /// is routed to this channel, and it must be at least as wide as `T`.
+                        pub unsafe fn write_to<B, T>(
+                            mut self,
+                            address: u32,
+                            buffer: B,
+                        ) -> Transfer<crate::dma::R, B, $CX, ()>
+                        where
+                            B: ReadBuffer<Word = T>,
+                            T: Word,
+                        {
+                            let (ptr, len) = buffer.read_buffer();
+                            self.start::<T>(address, ptr as usize as u32, len, true, true, false);
+
+                            Transfer::r(buffer, self, (), len)
+                        }
+
+                        /// Starts a circular transfer that keeps filling the two halves of
+                        /// `buffer` with reads of the peripheral register at `address`
+                        ///
+                        /// MSIZE and PSIZE are selected from the element type `T`, so e.g.
+                        /// halves of `[u16; N]` receive half-word samples.
+                        ///
+                        /// # Safety
+                        ///
+                        /// `address` must be the data register of a peripheral whose DMA request
+                        /// is routed to this channel, and it must be at least as wide as `T`.
+                        pub unsafe fn circ_read_from<B, T>(
+                            mut self,
+                            address: u32,
+                            buffer: &'static mut [B; 2],
+                        ) -> CircBuffer<B, $CX>
+                        where
+                            B: AsMut<[T]>,
+                            T: Word,
+                        {
+                            let (ptr, len) = {
+                                let half = buffer[0].as_mut();
+                                (half.as_ptr() as usize as u32, half.len() * 2)
+                            };
+                            self.start::<T>(address, ptr, len, false, true, true);
+
+                            CircBuffer::new(buffer, self)
+                        }
+
+                        /// Starts a circular transfer that keeps writing the two halves of
+                        /// `buffer` to the peripheral register at `address`
+                        ///
+                        /// Both halves should be filled before calling this.
+                        ///
+                        /// # Safety
+                        ///
+                        /// `address` must be the data register of a peripheral whose DMA request
+                        /// is routed to this channel, and it must be at least as wide as `T`.
+                        pub unsafe fn circ_write_to<B, T>(
+                            mut self,
+                            address: u32,
+                            buffer: &'static mut [B; 2],
+                        ) -> CircWriteBuffer<B, $CX>
+                        where
+                            B: AsMut<[T]>,
+                            T: Word,
+                        {
+                            let (ptr, len) = {
+                                let half = buffer[0].as_mut();
+                                (half.as_ptr() as usize as u32, half.len() * 2)
+                            };
+                            // stale flags would be mistaken for sent halves
+                            self.ifcr().write(|w| w.$cgifX().set_bit());
+                            self.start::<T>(address, ptr, len, true, true, true);
+
+                            CircWriteBuffer::new(buffer, self)
+                        }
+
+                        /// Starts filling `buffer` with reads of the data register of the
+                        /// peripheral `P`, paced by its `S` request
+                        ///
+                        /// Only compiles if `request` is routed to this channel.
+                        pub fn read_request<P, S, B, T>(
+                            self,
+                            _request: Request<P, S>,
+                            buffer: B,
+                        ) -> Transfer<W, B, $CX, ()>
+                        where
+                            Request<P, S>: DmaChannel<Dma = $CX>,
+                            P: Target<S>,
+                            B: WriteBuffer<Word = T>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) the request is routed to this channel and `address` is
+                            // the data register it serves
+                            unsafe { self.read_from(P::address(), buffer) }
+                        }
+
+                        /// Starts writing the elements of `buffer` to the data register of the
+                        /// peripheral `P`, paced by its `S` request
+                        ///
+                        /// Only compiles if `request` is routed to this channel.
+                        pub fn write_request<P, S, B, T>(
+                            self,
+                            _request: Request<P, S>,
+                            buffer: B,
+                        ) -> Transfer<crate::dma::R, B, $CX, ()>
+                        where
+                            Request<P, S>: DmaChannel<Dma = $CX>,
+                            P: Target<S>,
+                            B: ReadBuffer<Word = T>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) see `read_request`
+                            unsafe { self.write_to(P::address(), buffer) }
+                        }
+
+                        /// Circular version of `read_request`, see `circ_read_from`
+                        pub fn circ_read_request<P, S, B, T>(
+                            self,
+                            _request: Request<P, S>,
+                            buffer: &'static mut [B; 2],
+                        ) -> CircBuffer<B, $CX>
+                        where
+                            Request<P, S>: DmaChannel<Dma = $CX>,
+                            P: Target<S>,
+                            B: AsMut<[T]>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) see `read_request`
+                            unsafe { self.circ_read_from(P::address(), buffer) }
+                        }
+
+                        /// Circular version of `write_request`, see `circ_write_to`
+                        pub fn circ_write_request<P, S, B, T>(
+                            self,
+                            _request: Request<P, S>,
+                            buffer: &'static mut [B; 2],
+                        ) -> CircWriteBuffer<B, $CX>
+                        where
+                            Request<P, S>: DmaChannel<Dma = $CX>,
+                            P: Target<S>,
+                            B: AsMut<[T]>,
+                            T: Word,
+                        {
+                            // NOTE(unsafe) see `read_request`
+                            unsafe { self.circ_write_to(P::address(), buffer) }
+                        }
+
+                        /// Same as `start`, serving the `S` request of the peripheral `P`
+                        pub(crate) fn start_request<P, S, T>(
+                            &mut self,
+                            memory: u32,
+                            len: usize,
+                            memory_to_peripheral: bool,
+                            minc: bool,
+                            circular: bool,
+                        ) where
+                            Request<P, S>: DmaChannel<Dma = $CX>,
+                            P: Target<S>,
+                            T: Word,
+                        {
+                            self.start::<T>(
+                                P::address(),
+                                memory,
+                                len,
+                                memory_to_peripheral,
+                                minc,
+                                circular,
+                            );
+                        }
+
+                        /// Configures and enables a peripheral transfer of `len` elements of
+                        /// type `T`
+                        pub(crate) fn start<T>(
+                            &mut self,
+                            peripheral: u32,
+                            memory: u32,
+                            len: usize,
+                            memory_to_peripheral: bool,
+                            minc: bool,
+                            circular: bool,
+                        ) where
+                            T: Word,
+                        {
+                            self.cpar().write(|w| unsafe { w.pa().bits(peripheral) });
+                            self.cmar().write(|w| unsafe { w.ma().bits(memory) });
+                            self.cndtr().write(|w| unsafe { w.ndt().bits(u16(len).unwrap()) });
+
+                            // TODO can we weaken this compiler barrier?
+                            // NOTE(compiler_fence) operations on the buffer should not be
+                            // reordered after the next statement, which starts the DMA transfer
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            self.ccr().modify(|_, w| unsafe {
+                                w.mem2mem()
+                                    .clear_bit()
+                                    .msize()
+                                    .bits(T::SIZE)
+                                    .psize()
+                                    .bits(T::SIZE)
+                                    .minc()
+                                    .bit(minc)
+                                    .pinc()
+                                    .clear_bit()
+                                    .circ()
+                                    .bit(circular)
+                                    .dir()
+                                    .bit(memory_to_peripheral)
+                                    .en()
+                                    .set_bit()
+                            });
+                        }
+
+                        fn mem2mem<T>(&mut self, src: u32, src_inc: bool, dst: u32, len: usize)
+                        where
+                            T: Word,
+                        {
+                            // the source goes in the "peripheral" address register
+                            self.cpar().write(|w| unsafe { w.pa().bits(src) });
+                            self.cmar().write(|w| unsafe { w.ma().bits(dst) });
+                            self.cndtr().write(|w| unsafe { w.ndt().bits(u16(len).unwrap()) });
+
+                            // TODO can we weaken this compiler barrier?
+                            // NOTE(compiler_fence) operations on the buffers should not be
+                            // reordered after the next statement, which starts the DMA transfer
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            self.ccr().modify(|_, w| unsafe {
+                                w.mem2mem()
+                                    .set_bit()
+                                    .msize()
+                                    .bits(T::SIZE)
+                                    .psize()
+                                    .bits(T::SIZE)
+                                    .minc()
+                                    .set_bit()
+                                    .pinc()
+                                    .bit(src_inc)
+                                    .circ()
+                                    .clear_bit()
+                                    .dir()
+                                    .clear_bit()
+                                    .en()
+                                    .set_bit()
+                            });
+                        }
+                    }
+
+                    impl<B, PAYLOAD> CircBuffer<B, $CX, PAYLOAD>
+                    where
+                        PAYLOAD: TransferPayload,
+                    {
+                        /// Stops the reception
+                        ///
+                        /// Returns the buffer, the channel and the peripheral.
+                        pub fn stop(mut self) -> (&'static mut [B; 2], $CX, PAYLOAD) {
+                            self.channel.stop();
+                            self.payload.stop();
+
+                            // NOTE(compiler_fence) operations on the buffer should not be
+                            // reordered before the previous statements, which stop the DMA
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            (self.buffer, self.channel, self.payload)
+                        }
+                    }
+
+                    impl<B, PAYLOAD> RingBuffer<B, $CX, PAYLOAD>
+                    where
+                        PAYLOAD: TransferPayload,
+                    {
+                        /// Stops the reception
+                        ///
+                        /// Returns the buffer, the channel and the peripheral.
+                        pub fn stop(mut self) -> (&'static mut [B; 2], $CX, PAYLOAD) {
+                            self.channel.stop();
+                            self.payload.stop();
+
+                            // NOTE(compiler_fence) see `CircBuffer::stop`
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            (self.buffer, self.channel, self.payload)
+                        }
                     }
 
-                    impl<B> CircBuffer<B, $CX> {
+                    impl<B, PAYLOAD> CircBuffer<B, $CX, PAYLOAD> {
                         /// Peeks into the readable half of the buffer
                         pub fn peek<R, F>(&mut self, f: F) -> Result<R, Error>
                             where
@@ -224,6 +970,10 @@ macro_rules! dma {
                         /// Returns the `Half` of the buffer that can be read
                         pub fn readable_half(&mut self) -> Result<Half, Error> {
                             let isr = self.channel.isr();
+
+                            if isr.$teifX().bit_is_set() {
+                                return Err(Error::TransferError);
+                            }
                             let first_half_is_done = isr.$htifX().bit_is_set();
                             let second_half_is_done = isr.$tcifX().bit_is_set();
 
@@ -258,28 +1008,334 @@ macro_rules! dma {
                         }
                     }
 
+                    impl<B, PAYLOAD> CircWriteBuffer<B, $CX, PAYLOAD>
+                    where
+                        PAYLOAD: TransferPayload,
+                    {
+                        /// Stops the transmission
+                        ///
+                        /// Returns the buffer, the channel and the peripheral.
+                        pub fn stop(mut self) -> (&'static mut [B; 2], $CX, PAYLOAD) {
+                            self.channel.stop();
+                            self.payload.stop();
+
+                            // NOTE(compiler_fence) operations on the buffer should not be
+                            // reordered before the previous statements, which stop the DMA
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            (self.buffer, self.channel, self.payload)
+                        }
+                    }
+
+                    impl<B, PAYLOAD> CircWriteBuffer<B, $CX, PAYLOAD> {
+                        /// Refills the half of the buffer that the DMA has just finished sending
+                        ///
+                        /// Returns `WouldBlock` until the DMA is done with a half that hasn't been
+                        /// refilled yet, and `Error::Underrun` if the DMA started sending the half
+                        /// before `f` returned.
+                        pub fn write<R, F>(&mut self, f: F) -> nb::Result<R, Error>
+                            where
+                            F: FnOnce(&mut B, Half) -> R,
+                        {
+                            let half_being_written = self.writable_half()?;
+
+                            let buf = match half_being_written {
+                                Half::First => &mut self.buffer[0],
+                                Half::Second => &mut self.buffer[1],
+                            };
+
+                            let ret = f(buf, half_being_written);
+
+                            // NOTE(compiler_fence) the writes to the buffer must be done before
+                            // checking whether the DMA caught up with them
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            self.writable = None;
+
+                            let isr = self.channel.isr();
+                            let first_half_is_done = isr.$htifX().bit_is_set();
+                            let second_half_is_done = isr.$tcifX().bit_is_set();
+
+                            if (half_being_written == Half::First && second_half_is_done) ||
+                                (half_being_written == Half::Second && first_half_is_done) {
+                                Err(nb::Error::Other(Error::Underrun))
+                            } else {
+                                Ok(ret)
+                            }
+                        }
+
+                        /// Returns the `Half` of the buffer that can be refilled
+                        pub fn writable_half(&mut self) -> nb::Result<Half, Error> {
+                            let isr = self.channel.isr();
+
+                            if isr.$teifX().bit_is_set() {
+                                return Err(nb::Error::Other(Error::TransferError));
+                            }
+                            let first_half_is_done = isr.$htifX().bit_is_set();
+                            let second_half_is_done = isr.$tcifX().bit_is_set();
+
+                            if first_half_is_done && second_half_is_done {
+                                return Err(nb::Error::Other(Error::Underrun));
+                            }
+
+                            let freed_half = match self.last_freed_half {
+                                Half::First if second_half_is_done => {
+                                    self.channel.ifcr().write(|w| w.$ctcifX().set_bit());
+                                    Some(Half::Second)
+                                }
+                                Half::Second if first_half_is_done => {
+                                    self.channel.ifcr().write(|w| w.$chtifX().set_bit());
+                                    Some(Half::First)
+                                }
+                                _ => None,
+                            };
+
+                            if let Some(half) = freed_half {
+                                self.last_freed_half = half;
+
+                                // the DMA is now sending the half that was not refilled
+                                if self.writable.replace(half).is_some() {
+                                    return Err(nb::Error::Other(Error::Underrun));
+                                }
+                            }
+
+                            self.writable.ok_or(nb::Error::WouldBlock)
+                        }
+                    }
+
+                    impl<B, PAYLOAD> RingBuffer<B, $CX, PAYLOAD> where B: AsRef<[u8]> {
+                        /// Returns the number of bytes that can be read
+                        pub fn len(&mut self) -> Result<usize, Error> {
+                            self.available()
+                        }
+
+                        /// Returns `true` if there are no bytes to read
+                        pub fn is_empty(&mut self) -> Result<bool, Error> {
+                            self.available().map(|n| n == 0)
+                        }
+
+                        /// Copies the received bytes into `buf`, without consuming them
+                        ///
+                        /// Returns the number of bytes copied
+                        pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                            let n = cmp::min(self.available()?, buf.len());
+
+                            // NOTE(compiler_fence) reads of `buffer` should not be reordered
+                            // before the previous statement, which reads the DMA position
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            let half = self.buffer[0].as_ref().len();
+                            for (i, byte) in buf[..n].iter_mut().enumerate() {
+                                let index = (self.read + i) % (2 * half);
+                                *byte = if index < half {
+                                    self.buffer[0].as_ref()[index]
+                                } else {
+                                    self.buffer[1].as_ref()[index - half]
+                                };
+                            }
+
+                            // NOTE(compiler_fence) the bytes must be copied before checking that
+                            // the DMA didn't overwrite them in the meantime
+                            atomic::compiler_fence(Ordering::SeqCst);
+
+                            // the DMA may have lapped the reader while we were copying
+                            self.available()?;
+
+                            Ok(n)
+                        }
+
+                        /// Moves the received bytes into `buf`
+                        ///
+                        /// Returns the number of bytes read, which is zero if nothing has been
+                        /// received since the last call.
+                        ///
+                        /// If the DMA has overwritten bytes that were not read yet,
+                        /// `Error::Overrun` is returned and the reader skips to the most recently
+                        /// received byte, dropping the buffered data.
+                        pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                            let n = self.peek(buf)?;
+
+                            let capacity = 2 * self.buffer[0].as_ref().len();
+                            self.read += n;
+                            // the counter may report a full buffer before the transfer complete
+                            // flag is raised; the reader only wraps around after the DMA has
+                            if self.read >= capacity && self.halves >= 2 {
+                                self.read -= capacity;
+                                self.halves -= 2;
+                            }
+
+                            Ok(n)
+                        }
+
+                        /// Number of received bytes not yet read
+                        fn available(&mut self) -> Result<usize, Error> {
+                            let capacity = 2 * self.buffer[0].as_ref().len();
+                            let written = self.written()?;
+
+                            if written < self.read || written - self.read > capacity {
+                                self.overrun()
+                            } else {
+                                Ok(written - self.read)
+                            }
+                        }
+
+                        /// Number of bytes written by the DMA since the reader last wrapped
+                        /// around; the halves it finishes are counted in `halves`
+                        fn written(&mut self) -> Result<usize, Error> {
+                            let half = self.buffer[0].as_ref().len();
+                            let mut retried = false;
+
+                            loop {
+                                let cndtr = self.channel.get_cndtr() as usize;
+                                let isr = self.channel.isr();
+
+                                if isr.$teifX().bit_is_set() {
+                                    return Err(Error::TransferError);
+                                }
+
+                                let ht = isr.$htifX().bit_is_set();
+                                let tc = isr.$tcifX().bit_is_set();
+                                if ht || tc {
+                                    self.channel.ifcr().write(|w| {
+                                        w.$chtifX().bit(ht).$ctcifX().bit(tc)
+                                    });
+
+                                    // both halves were finished since the last call, or a flag
+                                    // was cleared elsewhere: the number of laps is unknown
+                                    let second_half = self.halves % 2 == 1;
+                                    if ht && tc || tc != second_half {
+                                        return self.overrun();
+                                    }
+
+                                    self.halves = self.halves.saturating_add(1);
+                                    continue;
+                                }
+
+                                let position = 2 * half - cndtr;
+                                if (position >= half) == (self.halves % 2 == 1) {
+                                    return Ok((self.halves / 2)
+                                        .saturating_mul(2 * half)
+                                        .saturating_add(position));
+                                }
+
+                                // the counter crossed a boundary whose flag wasn't raised yet when
+                                // the status was read; if it still isn't, it was cleared elsewhere
+                                if retried {
+                                    return self.overrun();
+                                }
+                                retried = true;
+                            }
+                        }
+
+                        /// Skips the reader to the DMA's position and reports the lost data
+                        fn overrun<T>(&mut self) -> Result<T, Error> {
+                            let half = self.buffer[0].as_ref().len();
+                            let position = 2 * half - self.channel.get_cndtr() as usize;
+
+                            self.read = position;
+                            self.halves = if position >= half { 1 } else { 0 };
+
+                            Err(Error::Overrun)
+                        }
+                    }
+
+                    impl Channel for $CX {
+                        fn stop(&mut self) {
+                            self.ccr().modify(|_, w| w.en().clear_bit());
+                            // stale flags would be mistaken for the end of the next transfer
+                            self.ifcr().write(|w| w.$cgifX().set_bit());
+                        }
+                    }
+
                     impl<BUFFER, PAYLOAD, MODE> Transfer<MODE, BUFFER, $CX, PAYLOAD> {
-                        pub fn is_done(&self) -> bool {
-                            self.channel.isr().$tcifX().bit_is_set()
+                        /// Returns `Ok(true)` once the transfer is complete
+                        ///
+                        /// A transfer error (an access to a reserved address) is reported as
+                        /// `Error::TransferError`
+                        pub fn is_done(&self) -> Result<bool, Error> {
+                            let isr = self.channel.isr();
+                            let cndtr = self.channel.get_cndtr();
+
+                            // the flags may have been cleared by `on_interrupt`; the hardware
+                            // disables the channel on a transfer error
+                            if isr.$teifX().bit_is_set() ||
+                                (!self.channel.is_enabled() && cndtr != 0) {
+                                Err(Error::TransferError)
+                            } else {
+                                Ok(isr.$tcifX().bit_is_set() || cndtr == 0)
+                            }
                         }
 
-                        pub fn wait(mut self) -> (BUFFER, $CX, PAYLOAD) {
-                            // XXX should we check for transfer errors here?
-                            // The manual says "A DMA transfer error can be generated by reading
-                            // from or writing to a reserved address space". I think it's impossible
-                            // to get to that state with our type safe API and *safe* Rust.
-                            while !self.is_done() {}
+                        /// Returns `Ok(true)` once (at least) half of the transfer is done
+                        pub fn is_half_done(&self) -> Result<bool, Error> {
+                            if self.is_done()? {
+                                return Ok(true);
+                            }
+
+                            let transferred = self.len - self.channel.get_cndtr() as usize;
 
-                            self.channel.ifcr().write(|w| w.$cgifX().set_bit());
+                            Ok(self.channel.isr().$htifX().bit_is_set() ||
+                               transferred >= self.len / 2)
+                        }
+
+                        /// Clears the pending flags of the channel and returns the event that
+                        /// raised the interrupt
+                        ///
+                        /// See the channel's `on_interrupt`. `is_done`, `is_half_done` and
+                        /// `wait` keep working after the flags have been cleared.
+                        pub fn on_interrupt(&mut self) -> Option<Event> {
+                            self.channel.on_interrupt()
+                        }
+                    }
+
+                    impl<BUFFER, PAYLOAD, MODE> Transfer<MODE, BUFFER, $CX, PAYLOAD>
+                    where
+                        PAYLOAD: TransferPayload,
+                    {
+                        /// Blocks until the transfer is complete
+                        ///
+                        /// On a transfer error the channel is stopped and the buffer, the channel
+                        /// and the payload are returned along with the error.
+                        pub fn wait(
+                            self,
+                        ) -> Result<(BUFFER, $CX, PAYLOAD), (Error, BUFFER, $CX, PAYLOAD)> {
+                            let error = loop {
+                                match self.is_done() {
+                                    Ok(true) => break None,
+                                    Ok(false) => {}
+                                    Err(error) => break Some(error),
+                                }
+                            };
 
-                            self.channel.ccr().modify(|_, w| w.en().clear_bit());
+                            let (buffer, channel, payload, _) = self.stop();
+
+                            match error {
+                                None => Ok((buffer, channel, payload)),
+                                Some(error) => Err((error, buffer, channel, payload)),
+                            }
+                        }
+
+                        /// Stops the transfer right away, whether it's done or not
+                        ///
+                        /// The payload is told to stop issuing DMA requests as well. Returns the
+                        /// buffer, the channel, the payload and the number of items that were
+                        /// transferred
+                        pub fn stop(mut self) -> (BUFFER, $CX, PAYLOAD, usize) {
+                            self.channel.stop();
+                            self.payload.stop();
+
+                            let transferred = self.len - self.channel.get_cndtr() as usize;
+
+                            self.channel.ifcr().write(|w| w.$cgifX().set_bit());
 
                             // TODO can we weaken this compiler barrier?
                             // NOTE(compiler_fence) operations on `buffer` should not be reordered
-                            // before the previous statement, which marks the DMA transfer as done
+                            // before the previous statements, which stop the DMA transfer
                             atomic::compiler_fence(Ordering::SeqCst);
 
-                            (self.buffer, self.channel, self.payload)
+                            let (buffer, channel, payload) = self.into_parts();
+
+                            (buffer, channel, payload, transferred)
                         }
                     }
 
@@ -298,15 +1354,42 @@ macro_rules! dma {
                     }
                 )+
 
+                /// Disables the channels whose memory or peripheral address lies in `start..end`
+                pub(crate) fn abort_transfers(start: u32, end: u32) {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let enabled = unsafe { (*RCC::ptr()).ahbenr.read().$dmaXen().bit_is_set() };
+
+                    // without a clock the controller can't be moving data
+                    if !enabled {
+                        return;
+                    }
+
+                    // NOTE(unsafe) a channel in use is only ever disabled here, which is what
+                    // dropping its transfer would do
+                    let dma = unsafe { &*$DMAX::ptr() };
+                    $(
+                        if dma.$ccrX.read().en().bit_is_set() {
+                            let cmar = dma.$cmarX.read().bits();
+                            let cpar = dma.$cparX.read().bits();
+
+                            if (start..end).contains(&cmar) || (start..end).contains(&cpar) {
+                                dma.$ccrX.modify(|_, w| w.en().clear_bit());
+                            }
+                        }
+                    )+
+                }
+
                 impl DmaExt for $DMAX {
                     type Channels = Channels;
 
                     fn split(self, ahb: &mut AHB) -> Channels {
                         ahb.enr().modify(|_, w| w.$dmaXen().enabled());
 
-                        // reset the DMA control registers (stops all on-going transfers)
+                        // reset the DMA control registers (stops all on-going transfers) and
+                        // use medium as the default priority
                         $(
                             self.$ccrX.reset();
+                            self.$ccrX.write(|w| w.pl().medium());
                         )+
 
                         Channels((), $($CX { _0: () }),+)
@@ -320,107 +1403,379 @@ macro_rules! dma {
 dma! {
     DMA1: (dma1, dma1en, dma1rst, {
         C1: (
+            DMA1_CHANNEL1,
             ccr1, CCR1,
             cndtr1, CNDTR1,
             cpar1, CPAR1,
             cmar1, CMAR1,
-            htif1, tcif1,
-            chtif1, ctcif1, cgif1
+            gif1, htif1, tcif1, teif1,
+            chtif1, ctcif1, cteif1, cgif1
         ),
         C2: (
+            DMA1_CHANNEL2,
             ccr2, CCR2,
             cndtr2, CNDTR2,
             cpar2, CPAR2,
             cmar2, CMAR2,
-            htif2, tcif2,
-            chtif2, ctcif2, cgif2
+            gif2, htif2, tcif2, teif2,
+            chtif2, ctcif2, cteif2, cgif2
         ),
         C3: (
+            DMA1_CHANNEL3,
             ccr3, CCR3,
             cndtr3, CNDTR3,
             cpar3, CPAR3,
             cmar3, CMAR3,
-            htif3, tcif3,
-            chtif3, ctcif3, cgif3
+            gif3, htif3, tcif3, teif3,
+            chtif3, ctcif3, cteif3, cgif3
         ),
         C4: (
+            DMA1_CHANNEL4,
             ccr4, CCR4,
             cndtr4, CNDTR4,
             cpar4, CPAR4,
             cmar4, CMAR4,
-            htif4, tcif4,
-            chtif4, ctcif4, cgif4
+            gif4, htif4, tcif4, teif4,
+            chtif4, ctcif4, cteif4, cgif4
         ),
         C5: (
+            DMA1_CHANNEL5,
             ccr5, CCR5,
             cndtr5, CNDTR5,
             cpar5, CPAR5,
             cmar5, CMAR5,
-            htif5, tcif5,
-            chtif5, ctcif5, cgif5
+            gif5, htif5, tcif5, teif5,
+            chtif5, ctcif5, cteif5, cgif5
         ),
         C6: (
+            DMA1_CHANNEL6,
             ccr6, CCR6,
             cndtr6, CNDTR6,
             cpar6, CPAR6,
             cmar6, CMAR6,
-            htif6, tcif6,
-            chtif6, ctcif6, cgif6
+            gif6, htif6, tcif6, teif6,
+            chtif6, ctcif6, cteif6, cgif6
         ),
         C7: (
+            DMA1_CHANNEL7,
             ccr7, CCR7,
             cndtr7, CNDTR7,
             cpar7, CPAR7,
             cmar7, CMAR7,
-            htif7, tcif7,
-            chtif7, ctcif7, cgif7
+            gif7, htif7, tcif7, teif7,
+            chtif7, ctcif7, cteif7, cgif7
         ),
     }),
 
     DMA2: (dma2, dma2en, dma2rst, {
         C1: (
+            DMA2_CHANNEL1,
             ccr1, CCR1,
             cndtr1, CNDTR1,
             cpar1, CPAR1,
             cmar1, CMAR1,
-            htif1, tcif1,
-            chtif1, ctcif1, cgif1
+            gif1, htif1, tcif1, teif1,
+            chtif1, ctcif1, cteif1, cgif1
         ),
         C2: (
+            DMA2_CHANNEL2,
             ccr2, CCR2,
             cndtr2, CNDTR2,
             cpar2, CPAR2,
             cmar2, CMAR2,
-            htif2, tcif2,
-            chtif2, ctcif2, cgif2
+            gif2, htif2, tcif2, teif2,
+            chtif2, ctcif2, cteif2, cgif2
         ),
         C3: (
+            DMA2_CHANNEL3,
             ccr3, CCR3,
             cndtr3, CNDTR3,
             cpar3, CPAR3,
             cmar3, CMAR3,
-            htif3, tcif3,
-            chtif3, ctcif3, cgif3
+            gif3, htif3, tcif3, teif3,
+            chtif3, ctcif3, cteif3, cgif3
         ),
         C4: (
+            DMA2_CHANNEL4_5,
             ccr4, CCR4,
             cndtr4, CNDTR4,
             cpar4, CPAR4,
             cmar4, CMAR4,
-            htif4, tcif4,
-            chtif4, ctcif4, cgif4
+            gif4, htif4, tcif4, teif4,
+            chtif4, ctcif4, cteif4, cgif4
         ),
         C5: (
+            DMA2_CHANNEL4_5,
             ccr5, CCR5,
             cndtr5, CNDTR5,
             cpar5, CPAR5,
             cmar5, CMAR5,
-            htif5, tcif5,
-            chtif5, ctcif5, cgif5
+            gif5, htif5, tcif5, teif5,
+            chtif5, ctcif5, cteif5, cgif5
         ),
     }),
 }
 
+/// Maps a DMA request to the only channel that can serve it
+///
+/// Implemented by the `Rx` / `Tx` halves of the serial interfaces and by `Request`
 pub trait DmaChannel {
-    type Dma;
+    type Dma: Channel;
+}
+
+/// DMA request line `SIGNAL` of `PERIPHERAL`, e.g. `Request<TIM2, signal::Up>`
+///
+/// Use `RequestChannel<PERIPHERAL, SIGNAL>` to name the channel wired to a request.
+/// Requests that are not routed to any channel don't implement `DmaChannel`.
+///
+/// The `*_request` methods of the channels start transfers on the data register of peripherals
+/// that implement `Target`, e.g. `c1.circ_read_request(Request::<ADC1, signal::Eoc>::new(), buf)`.
+pub struct Request<PERIPHERAL, SIGNAL> {
+    _peripheral: PhantomData<PERIPHERAL>,
+    _signal: PhantomData<SIGNAL>,
+}
+
+impl<PERIPHERAL, SIGNAL> Request<PERIPHERAL, SIGNAL> {
+    pub fn new() -> Self {
+        Request {
+            _peripheral: PhantomData,
+            _signal: PhantomData,
+        }
+    }
+}
+
+/// Channel wired to the `SIGNAL` request of `PERIPHERAL`
+pub type RequestChannel<PERIPHERAL, SIGNAL> = <Request<PERIPHERAL, SIGNAL> as DmaChannel>::Dma;
+
+/// Peripheral register served by the `SIGNAL` DMA request
+///
+/// # Safety
+///
+/// `address` must return the address of the data register that the `SIGNAL` request of this
+/// peripheral asks to be read or written.
+pub unsafe trait Target<SIGNAL> {
+    fn address() -> u32;
+}
+
+/// DMA request signals
+pub mod signal {
+    /// Data received (SPI, I2C, USART)
+    pub struct Rx;
+    /// Data to transmit (SPI, I2C, USART)
+    pub struct Tx;
+    /// End of a regular conversion (ADC)
+    pub struct Eoc;
+    /// Data transfer (SDIO)
+    pub struct Data;
+    /// Timer update
+    pub struct Up;
+    /// Timer trigger
+    pub struct Trig;
+    /// Timer commutation
+    pub struct Com;
+    /// Capture / compare channel 1 (timers) or channel 1 (DAC)
+    pub struct Ch1;
+    /// Capture / compare channel 2 (timers) or channel 2 (DAC)
+    pub struct Ch2;
+    /// Capture / compare channel 3
+    pub struct Ch3;
+    /// Capture / compare channel 4
+    pub struct Ch4;
+}
+
+macro_rules! requests {
+    ($($PERIPHERAL:ident: { $($SIGNAL:ident => $dmaX:ident::$CX:ident,)+ },)+) => {
+        $(
+            $(
+                impl DmaChannel for Request<crate::device::$PERIPHERAL, signal::$SIGNAL> {
+                    type Dma = $dmaX::$CX;
+                }
+            )+
+        )+
+    }
+}
+
+macro_rules! targets {
+    ($($PERIPHERAL:ident: [$($SIGNAL:ident),+],)+) => {
+        $(
+            $(
+                unsafe impl Target<signal::$SIGNAL> for crate::device::$PERIPHERAL {
+                    fn address() -> u32 {
+                        // NOTE(unsafe) only the address of the register is taken
+                        unsafe { &(*crate::device::$PERIPHERAL::ptr()).dr as *const _ as usize as u32 }
+                    }
+                }
+            )+
+        )+
+    }
+}
+
+targets! {
+    ADC1: [Eoc],
+    ADC3: [Eoc],
+    SPI1: [Rx, Tx],
+    SPI2: [Rx, Tx],
+    SPI3: [Rx, Tx],
+    USART1: [Rx, Tx],
+    USART2: [Rx, Tx],
+    USART3: [Rx, Tx],
+    UART4: [Rx, Tx],
+    I2C1: [Rx, Tx],
+    I2C2: [Rx, Tx],
+}
+
+requests! {
+    ADC1: {
+        Eoc => dma1::C1,
+    },
+    SPI1: {
+        Rx => dma1::C2,
+        Tx => dma1::C3,
+    },
+    SPI2: {
+        Rx => dma1::C4,
+        Tx => dma1::C5,
+    },
+    USART1: {
+        Rx => dma1::C5,
+        Tx => dma1::C4,
+    },
+    USART2: {
+        Rx => dma1::C6,
+        Tx => dma1::C7,
+    },
+    USART3: {
+        Rx => dma1::C3,
+        Tx => dma1::C2,
+    },
+    I2C1: {
+        Rx => dma1::C7,
+        Tx => dma1::C6,
+    },
+    I2C2: {
+        Rx => dma1::C5,
+        Tx => dma1::C4,
+    },
+    TIM1: {
+        Ch1 => dma1::C2,
+        Ch2 => dma1::C3,
+        Ch3 => dma1::C6,
+        Ch4 => dma1::C4,
+        Trig => dma1::C4,
+        Com => dma1::C4,
+        Up => dma1::C5,
+    },
+    TIM2: {
+        Ch1 => dma1::C5,
+        Ch2 => dma1::C7,
+        Ch3 => dma1::C1,
+        Ch4 => dma1::C7,
+        Up => dma1::C2,
+    },
+    TIM3: {
+        Ch1 => dma1::C6,
+        Ch3 => dma1::C2,
+        Ch4 => dma1::C3,
+        Trig => dma1::C6,
+        Up => dma1::C3,
+    },
+    TIM4: {
+        Ch1 => dma1::C1,
+        Ch2 => dma1::C4,
+        Ch3 => dma1::C5,
+        Up => dma1::C7,
+    },
+    // high density devices
+    ADC3: {
+        Eoc => dma2::C5,
+    },
+    SPI3: {
+        Rx => dma2::C1,
+        Tx => dma2::C2,
+    },
+    UART4: {
+        Rx => dma2::C3,
+        Tx => dma2::C5,
+    },
+    SDIO: {
+        Data => dma2::C4,
+    },
+    DAC: {
+        Ch1 => dma2::C3,
+        Ch2 => dma2::C4,
+    },
+    TIM5: {
+        Ch1 => dma2::C5,
+        Ch2 => dma2::C4,
+        Ch3 => dma2::C2,
+        Ch4 => dma2::C1,
+        Trig => dma2::C1,
+        Up => dma2::C2,
+    },
+    TIM6: {
+        Up => dma2::C3,
+    },
+    TIM7: {
+        Up => dma2::C4,
+    },
+    TIM8: {
+        Ch1 => dma2::C3,
+        Ch2 => dma2::C5,
+        Ch3 => dma2::C1,
+        Ch4 => dma2::C2,
+        Trig => dma2::C2,
+        Com => dma2::C2,
+        Up => dma2::C1,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use core::any::TypeId;
+
+    use crate::device::{ADC1, I2C1, I2C2, SPI1, SPI2, SPI3, TIM1, UART4, USART1, USART2, USART3};
+
+    use super::{dma1, dma2, signal, RequestChannel};
+
+    fn same<A, B>() -> bool
+    where
+        A: 'static,
+        B: 'static,
+    {
+        TypeId::of::<A>() == TypeId::of::<B>()
+    }
+
+    #[test]
+    fn serial_channels() {
+        assert!(same::<RequestChannel<USART1, signal::Rx>, dma1::C5>());
+        assert!(same::<RequestChannel<USART1, signal::Tx>, dma1::C4>());
+        assert!(same::<RequestChannel<USART2, signal::Rx>, dma1::C6>());
+        assert!(same::<RequestChannel<USART2, signal::Tx>, dma1::C7>());
+        assert!(same::<RequestChannel<USART3, signal::Rx>, dma1::C3>());
+        assert!(same::<RequestChannel<USART3, signal::Tx>, dma1::C2>());
+        assert!(same::<RequestChannel<UART4, signal::Rx>, dma2::C3>());
+        assert!(same::<RequestChannel<UART4, signal::Tx>, dma2::C5>());
+    }
+
+    #[test]
+    fn spi_and_i2c_channels() {
+        assert!(same::<RequestChannel<SPI1, signal::Rx>, dma1::C2>());
+        assert!(same::<RequestChannel<SPI1, signal::Tx>, dma1::C3>());
+        assert!(same::<RequestChannel<SPI2, signal::Rx>, dma1::C4>());
+        assert!(same::<RequestChannel<SPI2, signal::Tx>, dma1::C5>());
+        assert!(same::<RequestChannel<SPI3, signal::Rx>, dma2::C1>());
+        assert!(same::<RequestChannel<SPI3, signal::Tx>, dma2::C2>());
+        assert!(same::<RequestChannel<I2C1, signal::Rx>, dma1::C7>());
+        assert!(same::<RequestChannel<I2C1, signal::Tx>, dma1::C6>());
+        assert!(same::<RequestChannel<I2C2, signal::Rx>, dma1::C5>());
+        assert!(same::<RequestChannel<I2C2, signal::Tx>, dma1::C4>());
+    }
+
+    #[test]
+    fn requests_sharing_a_channel() {
+        assert!(same::<RequestChannel<ADC1, signal::Eoc>, dma1::C1>());
+        assert!(same::<RequestChannel<TIM1, signal::Ch4>, dma1::C4>());
+        assert!(same::<RequestChannel<TIM1, signal::Trig>, dma1::C4>());
+        assert!(same::<RequestChannel<TIM1, signal::Com>, dma1::C4>());
+    }
 }
@@ -6,21 +6,25 @@
 //! 
 //! #![deny(unsafe_code)]
 //! #![deny(warnings)]
+//! #![no_main]
 //! #![no_std]
 //! 
-//! extern crate cortex_m;
-//! #[macro_use(block)]
-//! extern crate nb;
-//! extern crate panic_abort;
-//! extern crate stm32f103xx_hal as hal;
+//! extern crate panic_halt;
 //! 
 //! use cortex_m::asm;
-//! use hal::prelude::*;
-//! use hal::serial::Serial;
-//! use hal::stm32f103xx;
 //! 
-//! fn main() {
-//!     let p = stm32f103xx::Peripherals::take().unwrap();
+//! use nb::block;
+//! 
+//! use stm32f103xx_hal::{
+//!     prelude::*,
+//!     device,
+//!     serial::{Config, Serial},
+//! };
+//! use cortex_m_rt::entry;
+//! 
+//! #[entry]
+//! fn main() -> ! {
+//!     let p = device::Peripherals::take().unwrap();
 //! 
 //!     let mut flash = p.FLASH.constrain();
 //!     let mut rcc = p.RCC.constrain();
@@ -52,10 +56,10 @@
 //!         p.USART3,
 //!         (tx, rx),
 //!         &mut afio.mapr,
-//!         9_600.bps(),
+//!         Config::default().baud_rate(9_600.bps()),
 //!         clocks,
 //!         &mut rcc.apb1,
-//!     );
+//!     ).unwrap();
 //! 
 //!     let (mut tx, mut rx) = serial.split();
 //! 
@@ -63,11 +67,13 @@
 //! 
 //!     block!(tx.write(sent)).ok();
 //! 
-//!     let received = block!(rx.read()).unwrap();
+//!     let received: u8 = block!(rx.read()).unwrap();
 //! 
 //!     assert_eq!(received, sent);
 //! 
 //!     asm::bkpt();
+//! 
+//!     loop {}
 //! }
 //! ```
 // Auto-generated. Do not modify.
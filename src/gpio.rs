@@ -4,7 +4,9 @@
 // that let you select different microcontroller packages
 
 use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
 
+use crate::hal::digital::{toggleable, InputPin, OutputPin, StatefulOutputPin};
 use crate::rcc::APB2;
 
 /// Extension trait to split a GPIO peripheral in independent pins and registers
@@ -46,6 +48,209 @@ pub struct Alternate<MODE> {
     _mode: PhantomData<MODE>,
 }
 
+/// Pin owned by the debug port (SWJ-DP) after reset (type state)
+///
+/// Use the methods of `afio::MAPR` to hand the pin over to the GPIO peripheral.
+pub struct Debugger;
+
+/// Mode of a pin that is under the control of the GPIO peripheral
+pub trait Active {}
+
+impl<MODE> Active for Input<MODE> {}
+
+impl<MODE> Active for Output<MODE> {}
+
+impl<MODE> Active for Alternate<MODE> {}
+
+impl Active for Analog {}
+
+/// Pin, or group of pins, whose configuration is locked until the next reset
+///
+/// The configuration methods (`into_*`) are no longer available but the pins can still be read and
+/// written.
+pub struct Locked<P> {
+    pins: P,
+}
+
+impl<P> Deref for Locked<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.pins
+    }
+}
+
+impl<P> DerefMut for Locked<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.pins
+    }
+}
+
+impl<P> OutputPin for Locked<P>
+where
+    P: OutputPin,
+{
+    fn set_high(&mut self) {
+        self.pins.set_high()
+    }
+
+    fn set_low(&mut self) {
+        self.pins.set_low()
+    }
+}
+
+impl<P> StatefulOutputPin for Locked<P>
+where
+    P: StatefulOutputPin,
+{
+    fn is_set_high(&self) -> bool {
+        self.pins.is_set_high()
+    }
+
+    fn is_set_low(&self) -> bool {
+        self.pins.is_set_low()
+    }
+}
+
+impl<P> toggleable::Default for Locked<P> where P: OutputPin + StatefulOutputPin {}
+
+impl<P> InputPin for Locked<P>
+where
+    P: InputPin,
+{
+    fn is_high(&self) -> bool {
+        self.pins.is_high()
+    }
+
+    fn is_low(&self) -> bool {
+        self.pins.is_low()
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<P> crate::hal1::digital::ErrorType for Locked<P>
+where
+    P: crate::hal1::digital::ErrorType,
+{
+    type Error = P::Error;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<P> crate::hal1::digital::OutputPin for Locked<P>
+where
+    P: crate::hal1::digital::OutputPin,
+{
+    fn set_high(&mut self) -> Result<(), P::Error> {
+        self.pins.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), P::Error> {
+        self.pins.set_low()
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<P> crate::hal1::digital::StatefulOutputPin for Locked<P>
+where
+    P: crate::hal1::digital::StatefulOutputPin,
+{
+    fn is_set_high(&mut self) -> Result<bool, P::Error> {
+        self.pins.is_set_high()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, P::Error> {
+        self.pins.is_set_low()
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<P> crate::hal1::digital::InputPin for Locked<P>
+where
+    P: crate::hal1::digital::InputPin,
+{
+    fn is_high(&mut self) -> Result<bool, P::Error> {
+        self.pins.is_high()
+    }
+
+    fn is_low(&mut self) -> Result<bool, P::Error> {
+        self.pins.is_low()
+    }
+}
+
+/// Pin that can be grouped with other pins of the same `PORT`
+pub trait GroupPin<PORT, MODE> {
+    /// Bit of this pin in the port registers
+    const MASK: u16;
+}
+
+/// Set of pins of the same `PORT`, all in the same `MODE`
+pub trait GroupPins<PORT, MODE> {
+    /// Bits of these pins in the port registers
+    const MASK: u16;
+}
+
+macro_rules! group_pins {
+    ($($P:ident),+) => {
+        impl<PORT, MODE, $($P),+> GroupPins<PORT, MODE> for ($($P,)+)
+        where
+            $($P: GroupPin<PORT, MODE>,)+
+        {
+            const MASK: u16 = 0 $(| $P::MASK)+;
+        }
+    }
+}
+
+group_pins!(P0, P1);
+group_pins!(P0, P1, P2);
+group_pins!(P0, P1, P2, P3);
+group_pins!(P0, P1, P2, P3, P4);
+group_pins!(P0, P1, P2, P3, P4, P5);
+group_pins!(P0, P1, P2, P3, P4, P5, P6);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14);
+group_pins!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15);
+
+/// Pins of one port that are read and written together
+///
+/// All the pins in the group are accessed with a single read of IDR / ODR or a single write to
+/// BSRR, which makes the group suitable for parallel buses. Values are given in port bit
+/// positions, e.g. for a group made of PB8 - PB15 the data byte goes in bits 8 - 15.
+pub struct PortGroup<PORT, MODE, PINS> {
+    pins: PINS,
+    _port: PhantomData<PORT>,
+    _mode: PhantomData<MODE>,
+}
+
+impl<PORT, MODE, PINS> PortGroup<PORT, MODE, PINS>
+where
+    PINS: GroupPins<PORT, MODE>,
+{
+    /// Groups `pins` so they can be accessed at once
+    pub fn new(pins: PINS) -> Self {
+        PortGroup {
+            pins,
+            _port: PhantomData,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Bits of the port that belong to this group
+    pub fn mask(&self) -> u16 {
+        PINS::MASK
+    }
+
+    /// Releases the pins
+    pub fn release(self) -> PINS {
+        self.pins
+    }
+}
+
 macro_rules! gpio {
     ($GPIOX:ident, $gpiox:ident, $gpioy:ident, $iopxenr:ident, $iopxrst:ident, $PXx:ident, [
         $($PXi:ident: ($pxi:ident, $i:expr, $MODE:ty, $CR:ident),)+
@@ -53,19 +258,26 @@ macro_rules! gpio {
         /// GPIO
         pub mod $gpiox {
             use core::marker::PhantomData;
+            #[cfg(feature = "embedded-hal-1")]
+            use core::convert::Infallible;
 
+            use cortex_m::interrupt;
             use crate::hal::digital::{InputPin, OutputPin, StatefulOutputPin, toggleable};
+            #[cfg(feature = "embedded-hal-1")]
+            use crate::hal1;
             use crate::device::{$gpioy, $GPIOX};
 
             use crate::rcc::APB2;
             use super::{
-                Alternate, Floating, GpioExt, Input,
+                Active, Alternate, Floating, GpioExt, GroupPin, GroupPins, Input, Locked,
                 OpenDrain,
                 Output,
                 PullDown, 
                 PullUp,
                 PushPull,
+                PortGroup,
                 Analog,
+                Debugger,
             };
 
             /// GPIO parts
@@ -104,8 +316,6 @@ macro_rules! gpio {
             }
 
             impl CRL {
-                // NOTE(allow) we get a warning on GPIOC because it only has 3 high pins
-                #[allow(dead_code)]
                 pub(crate) fn cr(&mut self) -> &$gpioy::CRL {
                     unsafe { &(*$GPIOX::ptr()).crl }
                 }
@@ -122,12 +332,58 @@ macro_rules! gpio {
                 }
             }
 
+            /// Runs the LCKR key sequence on the pins in `mask`
+            ///
+            /// Returns `false` if the port was already locked, as LCKR can only be written once per
+            /// reset, or if the key sequence didn't take.
+            fn lock(mask: u16) -> bool {
+                // NOTE(unsafe) the caller owns the pins in `mask`; LCKR can't be modified by
+                // anyone once LCKK reads as set
+                let lckr = unsafe { &(*$GPIOX::ptr()).lckr };
+
+                if lckr.read().lckk().bit_is_set() {
+                    return false;
+                }
+
+                let mask = u32::from(mask);
+
+                // an interrupt in the middle of the key sequence would abort it
+                interrupt::free(|_| {
+                    // key sequence: LCKK = 1, LCKK = 0, LCKK = 1, read, read
+                    lckr.write(|w| unsafe { w.bits((1 << 16) | mask) });
+                    lckr.write(|w| unsafe { w.bits(mask) });
+                    lckr.write(|w| unsafe { w.bits((1 << 16) | mask) });
+                    lckr.read();
+
+                    // LCKK reads as set once the sequence has succeeded
+                    lckr.read().lckk().bit_is_set()
+                })
+            }
+
             /// Partially erased pin
             pub struct $PXx<MODE> {
                 i: u8,
                 _mode: PhantomData<MODE>,
             }
 
+            impl<MODE> $PXx<MODE>
+            where
+                MODE: Active,
+            {
+                /// Locks the configuration of the pin until the next reset
+                ///
+                /// Only one lock operation per port is possible: the pin is handed back as an
+                /// error if the port has already been locked, or if locking failed. Use
+                /// `PortGroup::lock` to lock several pins of the same port.
+                pub fn lock(self) -> Result<Locked<Self>, Self> {
+                    if lock(1 << self.i) {
+                        Ok(Locked { pins: self })
+                    } else {
+                        Err(self)
+                    }
+                }
+            }
+
             impl<MODE> OutputPin for $PXx<Output<MODE>> {
                 fn set_high(&mut self) {
                     // NOTE(unsafe) atomic write to a stateless register
@@ -175,13 +431,150 @@ macro_rules! gpio {
                 }
             }
 
+            #[cfg(feature = "embedded-hal-1")]
+            impl<MODE> hal1::digital::ErrorType for $PXx<MODE> {
+                type Error = Infallible;
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<MODE> hal1::digital::OutputPin for $PXx<Output<MODE>> {
+                fn set_high(&mut self) -> Result<(), Infallible> {
+                    // NOTE(unsafe) atomic write to a stateless register
+                    unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << self.i)) }
+                    Ok(())
+                }
+
+                fn set_low(&mut self) -> Result<(), Infallible> {
+                    // NOTE(unsafe) atomic write to a stateless register
+                    unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << (16 + self.i))) }
+                    Ok(())
+                }
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<MODE> hal1::digital::StatefulOutputPin for $PXx<Output<MODE>> {
+                fn is_set_high(&mut self) -> Result<bool, Infallible> {
+                    hal1::digital::StatefulOutputPin::is_set_low(self).map(|low| !low)
+                }
+
+                fn is_set_low(&mut self) -> Result<bool, Infallible> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$GPIOX::ptr()).odr.read().bits() & (1 << self.i) == 0 })
+                }
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<MODE> hal1::digital::InputPin for $PXx<Input<MODE>> {
+                fn is_high(&mut self) -> Result<bool, Infallible> {
+                    hal1::digital::InputPin::is_low(self).map(|low| !low)
+                }
+
+                fn is_low(&mut self) -> Result<bool, Infallible> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << self.i) == 0 })
+                }
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl hal1::digital::InputPin for $PXx<Output<OpenDrain>> {
+                fn is_high(&mut self) -> Result<bool, Infallible> {
+                    hal1::digital::InputPin::is_low(self).map(|low| !low)
+                }
+
+                fn is_low(&mut self) -> Result<bool, Infallible> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << self.i) == 0 })
+                }
+            }
+
+            impl<MODE, PINS> PortGroup<$GPIOX, MODE, PINS>
+            where
+                PINS: GroupPins<$GPIOX, MODE>,
+            {
+                /// Locks the configuration of all the pins in the group until the next reset
+                ///
+                /// Only one lock operation per port is possible: the group is handed back as an
+                /// error if the port has already been locked, or if locking failed.
+                pub fn lock(self) -> Result<Locked<Self>, Self> {
+                    if lock(PINS::MASK) {
+                        Ok(Locked { pins: self })
+                    } else {
+                        Err(self)
+                    }
+                }
+            }
+
+            impl<MODE, PINS> PortGroup<$GPIOX, Output<MODE>, PINS>
+            where
+                PINS: GroupPins<$GPIOX, Output<MODE>>,
+            {
+                /// Drives high the pins of the group whose bits are set in `bits`
+                pub fn set_high(&mut self, bits: u16) {
+                    let bits = u32::from(bits & PINS::MASK);
+                    // NOTE(unsafe) atomic write to a stateless register
+                    unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(bits)) }
+                }
+
+                /// Drives low the pins of the group whose bits are set in `bits`
+                pub fn set_low(&mut self, bits: u16) {
+                    let bits = u32::from(bits & PINS::MASK);
+                    // NOTE(unsafe) atomic write to a stateless register
+                    unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(bits << 16)) }
+                }
+
+                /// Drives every pin of the group to the level of its bit in `value`
+                ///
+                /// The pins that have to go high and the ones that have to go low are updated in
+                /// a single store to BSRR. Pins of the port outside this group are not affected.
+                pub fn write_masked(&mut self, value: u16) {
+                    let set = u32::from(value & PINS::MASK);
+                    let reset = u32::from(!value & PINS::MASK);
+                    // NOTE(unsafe) atomic write to a stateless register
+                    unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(set | (reset << 16))) }
+                }
+
+                /// Returns the output levels of the group, masked to the group pins
+                pub fn read_output(&self) -> u16 {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let odr = unsafe { (*$GPIOX::ptr()).odr.read().bits() };
+                    odr as u16 & PINS::MASK
+                }
+            }
+
+            impl<MODE, PINS> PortGroup<$GPIOX, Input<MODE>, PINS>
+            where
+                PINS: GroupPins<$GPIOX, Input<MODE>>,
+            {
+                /// Returns the input levels of the group, masked to the group pins
+                pub fn read(&self) -> u16 {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let idr = unsafe { (*$GPIOX::ptr()).idr.read().bits() };
+                    idr as u16 & PINS::MASK
+                }
+            }
+
+            impl<PINS> PortGroup<$GPIOX, Output<OpenDrain>, PINS>
+            where
+                PINS: GroupPins<$GPIOX, Output<OpenDrain>>,
+            {
+                /// Returns the input levels of the group, masked to the group pins
+                pub fn read(&self) -> u16 {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let idr = unsafe { (*$GPIOX::ptr()).idr.read().bits() };
+                    idr as u16 & PINS::MASK
+                }
+            }
+
             $(
                 /// Pin
                 pub struct $PXi<MODE> {
                     _mode: PhantomData<MODE>,
                 }
 
-                impl<MODE> $PXi<MODE> {
+                impl<MODE> $PXi<MODE>
+                where
+                    MODE: Active,
+                {
                     /// Configures the pin to operate as an alternate function push pull output pin
                     pub fn into_alternate_push_pull(
                         self,
@@ -361,6 +754,40 @@ macro_rules! gpio {
                     }
                 }
 
+                impl<MODE> $PXi<MODE>
+                where
+                    MODE: Active,
+                {
+                    /// Locks the configuration of the pin until the next reset
+                    ///
+                    /// Only one lock operation per port is possible: the pin is handed back as an
+                    /// error if the port has already been locked, or if locking failed. Use
+                    /// `PortGroup::lock` to lock several pins of the same port.
+                    pub fn lock(self) -> Result<Locked<Self>, Self> {
+                        if lock(1 << $i) {
+                            Ok(Locked { pins: self })
+                        } else {
+                            Err(self)
+                        }
+                    }
+                }
+
+                impl<MODE> GroupPin<$GPIOX, MODE> for $PXi<MODE>
+                where
+                    MODE: Active,
+                {
+                    const MASK: u16 = 1 << $i;
+                }
+
+                impl $PXi<Debugger> {
+                    /// Hands the pin over from the debug port to the GPIO peripheral
+                    // NOTE(allow) only used on the pins that the SWJ-DP owns after reset
+                    #[allow(dead_code)]
+                    pub(crate) fn activate(self) -> $PXi<Input<Floating>> {
+                        $PXi { _mode: PhantomData }
+                    }
+                }
+
                 impl<MODE> $PXi<MODE> {
                     /// Erases the pin number from the type
                     ///
@@ -443,6 +870,89 @@ macro_rules! gpio {
                         unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 }
                     }
                 }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::ErrorType for $PXi<MODE> {
+                    type Error = Infallible;
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::OutputPin for $PXi<Output<MODE>> {
+                    fn set_high(&mut self) -> Result<(), Infallible> {
+                        // NOTE(unsafe) atomic write to a stateless register
+                        unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << $i)) }
+                        Ok(())
+                    }
+
+                    fn set_low(&mut self) -> Result<(), Infallible> {
+                        // NOTE(unsafe) atomic write to a stateless register
+                        unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << (16 + $i))) }
+                        Ok(())
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::StatefulOutputPin for $PXi<Output<MODE>> {
+                    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+                        hal1::digital::StatefulOutputPin::is_set_low(self).map(|low| !low)
+                    }
+
+                    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).odr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::OutputPin for $PXi<Alternate<MODE>> {
+                    fn set_high(&mut self) -> Result<(), Infallible> {
+                        // NOTE(unsafe) atomic write to a stateless register
+                        unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << $i)) }
+                        Ok(())
+                    }
+
+                    fn set_low(&mut self) -> Result<(), Infallible> {
+                        // NOTE(unsafe) atomic write to a stateless register
+                        unsafe { (*$GPIOX::ptr()).bsrr.write(|w| w.bits(1 << (16 + $i))) }
+                        Ok(())
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::StatefulOutputPin for $PXi<Alternate<MODE>> {
+                    fn is_set_high(&mut self) -> Result<bool, Infallible> {
+                        hal1::digital::StatefulOutputPin::is_set_low(self).map(|low| !low)
+                    }
+
+                    fn is_set_low(&mut self) -> Result<bool, Infallible> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).odr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> hal1::digital::InputPin for $PXi<Input<MODE>> {
+                    fn is_high(&mut self) -> Result<bool, Infallible> {
+                        hal1::digital::InputPin::is_low(self).map(|low| !low)
+                    }
+
+                    fn is_low(&mut self) -> Result<bool, Infallible> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl hal1::digital::InputPin for $PXi<Output<OpenDrain>> {
+                    fn is_high(&mut self) -> Result<bool, Infallible> {
+                        hal1::digital::InputPin::is_low(self).map(|low| !low)
+                    }
+
+                    fn is_low(&mut self) -> Result<bool, Infallible> {
+                        // NOTE(unsafe) atomic read with no side effects
+                        Ok(unsafe { (*$GPIOX::ptr()).idr.read().bits() & (1 << $i) == 0 })
+                    }
+                }
             )+
         }
     }
@@ -462,17 +972,17 @@ gpio!(GPIOA, gpioa, gpioa, iopaen, ioparst, PAx, [
     PA10: (pa10, 10, Input<Floating>, CRH),
     PA11: (pa11, 11, Input<Floating>, CRH),
     PA12: (pa12, 12, Input<Floating>, CRH),
-    PA13: (pa13, 13, Input<Floating>, CRH),
-    PA14: (pa14, 14, Input<Floating>, CRH),
-    PA15: (pa15, 15, Input<Floating>, CRH),
+    PA13: (pa13, 13, Debugger, CRH),
+    PA14: (pa14, 14, Debugger, CRH),
+    PA15: (pa15, 15, Debugger, CRH),
 ]);
 
 gpio!(GPIOB, gpiob, gpioa, iopben, iopbrst, PBx, [
     PB0: (pb0, 0, Input<Floating>, CRL),
     PB1: (pb1, 1, Input<Floating>, CRL),
     PB2: (pb2, 2, Input<Floating>, CRL),
-    PB3: (pb3, 3, Input<Floating>, CRL),
-    PB4: (pb4, 4, Input<Floating>, CRL),
+    PB3: (pb3, 3, Debugger, CRL),
+    PB4: (pb4, 4, Debugger, CRL),
     PB5: (pb5, 5, Input<Floating>, CRL),
     PB6: (pb6, 6, Input<Floating>, CRL),
     PB7: (pb7, 7, Input<Floating>, CRL),
@@ -487,7 +997,39 @@ gpio!(GPIOB, gpiob, gpioa, iopben, iopbrst, PBx, [
 ]);
 
 gpio!(GPIOC, gpioc, gpioa, iopcen, iopcrst, PCx, [
+    PC0: (pc0, 0, Input<Floating>, CRL),
+    PC1: (pc1, 1, Input<Floating>, CRL),
+    PC2: (pc2, 2, Input<Floating>, CRL),
+    PC3: (pc3, 3, Input<Floating>, CRL),
+    PC4: (pc4, 4, Input<Floating>, CRL),
+    PC5: (pc5, 5, Input<Floating>, CRL),
+    PC6: (pc6, 6, Input<Floating>, CRL),
+    PC7: (pc7, 7, Input<Floating>, CRL),
+    PC8: (pc8, 8, Input<Floating>, CRH),
+    PC9: (pc9, 9, Input<Floating>, CRH),
+    PC10: (pc10, 10, Input<Floating>, CRH),
+    PC11: (pc11, 11, Input<Floating>, CRH),
+    PC12: (pc12, 12, Input<Floating>, CRH),
     PC13: (pc13, 13, Input<Floating>, CRH),
     PC14: (pc14, 14, Input<Floating>, CRH),
     PC15: (pc15, 15, Input<Floating>, CRH),
 ]);
+
+gpio!(GPIOD, gpiod, gpioa, iopden, iopdrst, PDx, [
+    PD0: (pd0, 0, Input<Floating>, CRL),
+    PD1: (pd1, 1, Input<Floating>, CRL),
+    PD2: (pd2, 2, Input<Floating>, CRL),
+    PD3: (pd3, 3, Input<Floating>, CRL),
+    PD4: (pd4, 4, Input<Floating>, CRL),
+    PD5: (pd5, 5, Input<Floating>, CRL),
+    PD6: (pd6, 6, Input<Floating>, CRL),
+    PD7: (pd7, 7, Input<Floating>, CRL),
+    PD8: (pd8, 8, Input<Floating>, CRH),
+    PD9: (pd9, 9, Input<Floating>, CRH),
+    PD10: (pd10, 10, Input<Floating>, CRH),
+    PD11: (pd11, 11, Input<Floating>, CRH),
+    PD12: (pd12, 12, Input<Floating>, CRH),
+    PD13: (pd13, 13, Input<Floating>, CRH),
+    PD14: (pd14, 14, Input<Floating>, CRH),
+    PD15: (pd15, 15, Input<Floating>, CRH),
+]);
@@ -1,6 +1,9 @@
 //! Inter-Integrated Circuit (I2C) bus
 
 use crate::afio::MAPR;
+use crate::dma::{
+    signal, Channel, ReadBuffer, RequestChannel, Transfer, TransferPayload, WriteBuffer, R, W,
+};
 use crate::gpio::{Alternate, OpenDrain};
 use crate::gpio::gpiob::{PB10, PB11, PB6, PB7, PB8, PB9};
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
@@ -11,7 +14,7 @@ use crate::device::{I2C1, I2C2};
 use crate::device::DWT;
 
 /// I2C error
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Error {
     /// Bus error
     Bus,
@@ -21,12 +24,43 @@ pub enum Error {
     Acknowledge,
     /// Overrun/underrun
     Overrun,
+    /// The bus didn't respond in time (only reported by the DMA transfers and through the
+    /// `embedded-hal` 1.0 traits; the 0.2 traits report it as `nb::Error::WouldBlock`)
+    Timeout,
+    /// The DMA channel reported a transfer error
+    Dma,
+    /// A DMA read was given an empty buffer; the bus can't end a read before its first byte
+    Length,
     // Pec, // SMBUS mode only
-    // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
     #[doc(hidden)] _Extensible,
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl crate::hal1::i2c::Error for Error {
+    fn kind(&self) -> crate::hal1::i2c::ErrorKind {
+        use crate::hal1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self {
+            Error::Bus => ErrorKind::Bus,
+            Error::Arbitration => ErrorKind::ArbitrationLoss,
+            Error::Acknowledge => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Error::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<NbError<Error>> for Error {
+    fn from(error: NbError<Error>) -> Error {
+        match error {
+            Other(error) => error,
+            // the blocking operations only return `WouldBlock` when they time out
+            WouldBlock => Error::Timeout,
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum DutyCycle {
     Ratio2to1,
@@ -83,6 +117,7 @@ pub struct I2c<I2C, PINS> {
     pins: PINS,
     mode: Mode,
     pclk1: u32,
+    sysclk: u32,
 }
 
 pub struct BlockingI2c<I2C, PINS> {
@@ -105,7 +140,7 @@ impl<PINS> I2c<I2C1, PINS> {
         where
             PINS: Pins<I2C1>,
     {
-        mapr.mapr().modify(|_, w| w.i2c1_remap().bit(PINS::REMAP));
+        mapr.modify_mapr(|_, w| w.i2c1_remap().bit(PINS::REMAP));
         I2c::_i2c1(i2c, pins, mode, clocks, apb)
     }
 }
@@ -126,7 +161,7 @@ impl<PINS> BlockingI2c<I2C1, PINS> {
         where
             PINS: Pins<I2C1>,
     {
-        mapr.mapr().modify(|_, w| w.i2c1_remap().bit(PINS::REMAP));
+        mapr.modify_mapr(|_, w| w.i2c1_remap().bit(PINS::REMAP));
         BlockingI2c::_i2c1(i2c, pins, mode, clocks, apb,
                            start_timeout_us, start_retries, addr_timeout_us, data_timeout_us)
     }
@@ -183,19 +218,34 @@ pub fn blocking_i2c<I2C, PINS>(i2c: I2c<I2C, PINS>,
     };
 }
 
-macro_rules! wait_for_flag {
-    ($i2c:expr, $flag:ident) => {
+/// Decodes the error flags of an SR1 read
+macro_rules! sr1_error {
+    ($sr1:expr) => {
         {
-            let sr1 = $i2c.sr1.read();
+            let sr1 = $sr1;
 
             if sr1.berr().bit_is_set() {
-                Err(Other(Error::Bus))
+                Some(Error::Bus)
             } else if sr1.arlo().bit_is_set() {
-                Err(Other(Error::Arbitration))
+                Some(Error::Arbitration)
             } else if sr1.af().bit_is_set() {
-                Err(Other(Error::Acknowledge))
+                Some(Error::Acknowledge)
             } else if sr1.ovr().bit_is_set() {
-                Err(Other(Error::Overrun))
+                Some(Error::Overrun)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+macro_rules! wait_for_flag {
+    ($i2c:expr, $flag:ident) => {
+        {
+            let sr1 = $i2c.sr1.read();
+
+            if let Some(error) = sr1_error!(&sr1) {
+                Err(Other(error))
             } else if sr1.$flag().bit_is_set() {
                 Ok(())
             } else {
@@ -248,10 +298,11 @@ macro_rules! hal {
                     apb.rstr().modify(|_, w| w.$i2cXrst().clear_bit());
 
                     let pclk1 = clocks.pclk1().0;
+                    let sysclk = clocks.sysclk().0;
 
                     assert!(mode.get_frequency() <= 400_000);
 
-                    let mut i2c = I2c { i2c, pins, mode, pclk1 };
+                    let mut i2c = I2c { i2c, pins, mode, pclk1, sysclk };
                     i2c.init();
                     i2c
                 }
@@ -369,8 +420,19 @@ macro_rules! hal {
                     busy_wait_cycles!(self.nb.wait_after_sent_addr(), self.addr_timeout)
                 }
 
-                fn write_without_stop(&mut self, addr: u8, bytes: &[u8]) -> NbResult<(), Error> {
+                fn write_without_stop<'b, I>(&mut self, addr: u8, bytes: I) -> NbResult<(), Error>
+                where
+                    I: IntoIterator<Item = &'b u8>,
+                {
                     self.send_start_and_wait()?;
+                    self.write_after_start(addr, bytes)
+                }
+
+                /// Same as `write_without_stop` once the (repeated) START has been sent
+                fn write_after_start<'b, I>(&mut self, addr: u8, bytes: I) -> NbResult<(), Error>
+                where
+                    I: IntoIterator<Item = &'b u8>,
+                {
                     self.send_addr_and_wait(addr, false)?;
 
                     for byte in bytes {
@@ -394,22 +456,31 @@ macro_rules! hal {
                 }
             }
 
-            impl<PINS> Read for BlockingI2c<$I2CX, PINS> {
-                type Error = NbError<Error>;
-
-                fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            impl<PINS> BlockingI2c<$I2CX, PINS> {
+                /// Reads `len` bytes into `bytes` and sends a STOP condition, or a repeated START
+                /// if `stop` is false
+                fn read_into<'b, I>(
+                    &mut self,
+                    addr: u8,
+                    len: usize,
+                    mut bytes: I,
+                    stop: bool,
+                ) -> NbResult<(), Error>
+                where
+                    I: Iterator<Item = &'b mut u8>,
+                {
                     self.send_start_and_wait()?;
 
-                    match buffer.len() {
+                    match len {
                         1 => {
                             self.nb.send_addr(addr, true);
                             busy_wait_cycles!(wait_for_flag!(self.nb.i2c, addr), self.addr_timeout)?;
                             self.nb.i2c.cr1.modify(|_, w| w.ack().clear_bit());
                             let _ = self.nb.i2c.sr2.read();
-                            self.nb.send_stop();
+                            self.send_stop_or_start(stop);
 
                             busy_wait_cycles!(wait_for_flag!(self.nb.i2c, rx_ne), self.data_timeout)?;
-                            buffer[0] = self.nb.i2c.dr.read().dr().bits();
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
                         }
                         2 => {
                             self.nb.i2c.cr1.modify(|_, w| w.pos().set_bit().ack().set_bit());
@@ -417,16 +488,15 @@ macro_rules! hal {
                             self.nb.i2c.cr1.modify(|_, w| w.pos().clear_bit().ack().clear_bit());
 
                             busy_wait_cycles!(wait_for_flag!(self.nb.i2c, btf), self.data_timeout)?;
-                            self.nb.send_stop();
-                            buffer[0] = self.nb.i2c.dr.read().dr().bits();
-                            buffer[1] = self.nb.i2c.dr.read().dr().bits();
+                            self.send_stop_or_start(stop);
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
                         }
-                        buffer_len => {
+                        len => {
                             self.nb.i2c.cr1.modify(|_, w| w.ack().set_bit());
                             self.send_addr_and_wait(addr, true)?;
 
-                            let (first_bytes, last_two_bytes) = buffer.split_at_mut(buffer_len - 3);
-                            for byte in first_bytes {
+                            for byte in bytes.by_ref().take(len - 3) {
                                 self.nb.i2c.cr1.modify(|_, w| w.ack().set_bit());
                                 busy_wait_cycles!(wait_for_flag!(self.nb.i2c, rx_ne), self.data_timeout)?;
                                 *byte = self.nb.i2c.dr.read().dr().bits();
@@ -434,16 +504,32 @@ macro_rules! hal {
 
                             busy_wait_cycles!(wait_for_flag!(self.nb.i2c, btf), self.data_timeout)?;
                             self.nb.i2c.cr1.modify(|_, w| w.ack().clear_bit());
-                            last_two_bytes[0] = self.nb.i2c.dr.read().dr().bits();
-                            self.nb.send_stop();
-                            last_two_bytes[1] = self.nb.i2c.dr.read().dr().bits();
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
+                            self.send_stop_or_start(stop);
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
                             busy_wait_cycles!(wait_for_flag!(self.nb.i2c, rx_ne), self.data_timeout)?;
-                            last_two_bytes[2] = self.nb.i2c.dr.read().dr().bits();
+                            *bytes.next().unwrap() = self.nb.i2c.dr.read().dr().bits();
                         }
                     }
 
                     Ok(())
                 }
+
+                fn send_stop_or_start(&mut self, stop: bool) {
+                    if stop {
+                        self.nb.send_stop();
+                    } else {
+                        self.nb.send_start();
+                    }
+                }
+            }
+
+            impl<PINS> Read for BlockingI2c<$I2CX, PINS> {
+                type Error = NbError<Error>;
+
+                fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+                    self.read_into(addr, buffer.len(), buffer.iter_mut(), true)
+                }
             }
 
             impl<PINS> WriteRead for BlockingI2c<$I2CX, PINS> {
@@ -468,6 +554,91 @@ macro_rules! hal {
                     Ok(())
                 }
             }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<PINS> crate::hal1::i2c::ErrorType for BlockingI2c<$I2CX, PINS> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<PINS> crate::hal1::i2c::I2c for BlockingI2c<$I2CX, PINS> {
+                fn transaction(
+                    &mut self,
+                    addr: u8,
+                    operations: &mut [crate::hal1::i2c::Operation<'_>],
+                ) -> Result<(), Error> {
+                    use crate::hal1::i2c::Operation;
+
+                    let mut start = 0;
+                    let mut stop_pending = false;
+                    // a read followed by a write has already scheduled the repeated START
+                    let mut restarted = false;
+                    while start < operations.len() {
+                        let reading = match operations[start] {
+                            Operation::Read(_) => true,
+                            Operation::Write(_) => false,
+                        };
+
+                        // adjacent operations of the same kind are merged
+                        let end = operations[start..]
+                            .iter()
+                            .position(|op| match op {
+                                Operation::Read(_) => !reading,
+                                Operation::Write(_) => reading,
+                            })
+                            .map(|n| start + n)
+                            .unwrap_or(operations.len());
+                        let group = &mut operations[start..end];
+
+                        if reading {
+                            let len = group
+                                .iter()
+                                .map(|op| match op {
+                                    Operation::Read(buffer) => buffer.len(),
+                                    Operation::Write(_) => 0,
+                                })
+                                .sum();
+
+                            if len != 0 {
+                                // the STOP, or the repeated START of the next write, has to be
+                                // scheduled while the last bytes are still being received
+                                let last = end == operations.len();
+                                let bytes = group.iter_mut().flat_map(|op| match op {
+                                    Operation::Read(buffer) => buffer.iter_mut(),
+                                    Operation::Write(_) => (&mut []).iter_mut(),
+                                });
+                                self.read_into(addr, len, bytes, last)?;
+
+                                restarted = !last;
+                                stop_pending = false;
+                            }
+                        } else {
+                            let bytes = group.iter().flat_map(|op| match op {
+                                Operation::Write(bytes) => bytes.iter(),
+                                Operation::Read(_) => (&[]).iter(),
+                            });
+
+                            if restarted {
+                                busy_wait_cycles!(self.nb.wait_after_sent_start(), self.start_timeout)?;
+                                self.write_after_start(addr, bytes)?;
+                            } else {
+                                self.write_without_stop(addr, bytes)?;
+                            }
+
+                            restarted = false;
+                            stop_pending = true;
+                        }
+
+                        start = end;
+                    }
+
+                    if stop_pending {
+                        self.nb.send_stop();
+                    }
+
+                    Ok(())
+                }
+            }
         )+
     }
 }
@@ -476,3 +647,349 @@ hal! {
     I2C1: (_i2c1, i2c1en, i2c1rst),
     I2C2: (_i2c2, i2c2en, i2c2rst),
 }
+
+/// DMA transfer on the I2C bus
+///
+/// Unlike `dma::Transfer` this also watches the bus: a missing acknowledge or a lost arbitration
+/// terminates the transfer. Dropping it before it's done stops the channel and ends the transfer
+/// on the bus.
+pub struct DmaTransfer<MODE, BUFFER, CHANNEL, I2C, PINS>
+where
+    CHANNEL: Channel,
+{
+    transfer: Transfer<MODE, BUFFER, CHANNEL, I2c<I2C, PINS>>,
+    error: Option<Error>,
+    // the STOP condition was requested when the transfer started
+    stopped: bool,
+}
+
+macro_rules! dma {
+    ($($I2CX:ident,)+) => {
+        $(
+            impl<PINS> I2c<$I2CX, PINS> {
+                fn check_errors(&self) -> Result<(), Error> {
+                    match sr1_error!(&self.i2c.sr1.read()) {
+                        Some(error) => Err(error),
+                        None => Ok(()),
+                    }
+                }
+
+                /// Converts a timeout in microseconds into DWT cycles
+                fn cycles(&self, us: u32) -> u32 {
+                    us.saturating_mul(self.sysclk / 1_000_000)
+                }
+
+                /// Sends a (repeated) START condition followed by the address
+                ///
+                /// Gives up with `Error::Timeout` after `start_timeout` / `addr_timeout` DWT
+                /// cycles. The START isn't retried: resetting the peripheral would also drop the
+                /// DMA setup.
+                fn start_and_address(
+                    &mut self,
+                    addr: u8,
+                    read: bool,
+                    start_timeout: u32,
+                    addr_timeout: u32,
+                ) -> Result<(), Error> {
+                    self.send_start();
+                    busy_wait_cycles!(self.wait_after_sent_start(), start_timeout)?;
+                    self.send_addr(addr, read);
+                    busy_wait_cycles!(self.wait_after_sent_addr(), addr_timeout)?;
+                    Ok(())
+                }
+
+                /// Sends `buffer` using DMA
+                ///
+                /// The START condition and the acknowledge of the address are waited for at most
+                /// `start_timeout_us` and `addr_timeout_us`. An empty `buffer` only addresses the
+                /// slave, e.g. to probe for it.
+                pub fn write_dma<B>(
+                    mut self,
+                    mut chan: RequestChannel<$I2CX, signal::Tx>,
+                    addr: u8,
+                    buffer: B,
+                    start_timeout_us: u32,
+                    addr_timeout_us: u32,
+                ) -> DmaTransfer<R, B, RequestChannel<$I2CX, signal::Tx>, $I2CX, PINS>
+                where
+                    B: ReadBuffer<Word = u8>,
+                {
+                    let start_timeout = self.cycles(start_timeout_us);
+                    let addr_timeout = self.cycles(addr_timeout_us);
+
+                    let (error, len) = {
+                        // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                        let (ptr, len) = unsafe { buffer.read_buffer() };
+                        chan.start_request::<$I2CX, signal::Tx, u8>(
+                            ptr as usize as u32,
+                            len,
+                            true,
+                            true,
+                            false,
+                        );
+
+                        self.i2c.cr2.modify(|_, w| w.dmaen().set_bit());
+
+                        let error = self.start_and_address(addr, false, start_timeout, addr_timeout);
+
+                        (error.err(), len)
+                    };
+
+                    // BTF never gets set when no data byte is shifted out
+                    let stopped = len == 0 && error.is_none();
+                    if stopped {
+                        self.send_stop();
+                    }
+
+                    DmaTransfer {
+                        transfer: Transfer::r(buffer, chan, self, len),
+                        error,
+                        stopped,
+                    }
+                }
+
+                /// Fills `buffer` using DMA
+                ///
+                /// The START condition and the acknowledge of the address are waited for at most
+                /// `start_timeout_us` and `addr_timeout_us`. An empty `buffer` is rejected with
+                /// `Error::Length` before the bus is touched.
+                pub fn read_dma<B>(
+                    self,
+                    chan: RequestChannel<$I2CX, signal::Rx>,
+                    addr: u8,
+                    buffer: B,
+                    start_timeout_us: u32,
+                    addr_timeout_us: u32,
+                ) -> DmaTransfer<W, B, RequestChannel<$I2CX, signal::Rx>, $I2CX, PINS>
+                where
+                    B: WriteBuffer<Word = u8>,
+                {
+                    let start_timeout = self.cycles(start_timeout_us);
+                    let addr_timeout = self.cycles(addr_timeout_us);
+
+                    self.start_read_dma(chan, addr, buffer, Ok(()), start_timeout, addr_timeout)
+                }
+
+                /// Sends `bytes` and then fills `buffer` using DMA, after a repeated START
+                ///
+                /// `bytes` (e.g. a register address) are sent without DMA, waiting at most
+                /// `data_timeout_us` for each of them. See `read_dma` for the other timeouts and
+                /// for an empty `buffer`, which is rejected before `bytes` are sent.
+                pub fn write_read_dma<B>(
+                    mut self,
+                    chan: RequestChannel<$I2CX, signal::Rx>,
+                    addr: u8,
+                    bytes: &[u8],
+                    mut buffer: B,
+                    start_timeout_us: u32,
+                    addr_timeout_us: u32,
+                    data_timeout_us: u32,
+                ) -> DmaTransfer<W, B, RequestChannel<$I2CX, signal::Rx>, $I2CX, PINS>
+                where
+                    B: WriteBuffer<Word = u8>,
+                {
+                    let start_timeout = self.cycles(start_timeout_us);
+                    let addr_timeout = self.cycles(addr_timeout_us);
+                    let data_timeout = self.cycles(data_timeout_us);
+
+                    // NOTE(unsafe) only the length is looked at, the buffer isn't accessed
+                    let (_, len) = unsafe { buffer.write_buffer() };
+
+                    let written = if len == 0 {
+                        // `start_read_dma` rejects it
+                        Ok(())
+                    } else {
+                        self.start_and_address(addr, false, start_timeout, addr_timeout)
+                            .and_then(|_| {
+                                for byte in bytes {
+                                    busy_wait_cycles!(
+                                        wait_for_flag!(self.i2c, tx_e),
+                                        data_timeout
+                                    )?;
+                                    self.i2c.dr.write(|w| unsafe { w.dr().bits(*byte) });
+                                }
+                                // BTF never gets set when no data byte is shifted out
+                                if !bytes.is_empty() {
+                                    busy_wait_cycles!(wait_for_flag!(self.i2c, btf), data_timeout)?;
+                                }
+                                Ok(())
+                            })
+                    };
+
+                    self.start_read_dma(chan, addr, buffer, written, start_timeout, addr_timeout)
+                }
+
+                fn start_read_dma<B>(
+                    mut self,
+                    mut chan: RequestChannel<$I2CX, signal::Rx>,
+                    addr: u8,
+                    mut buffer: B,
+                    previous: Result<(), Error>,
+                    start_timeout: u32,
+                    addr_timeout: u32,
+                ) -> DmaTransfer<W, B, RequestChannel<$I2CX, signal::Rx>, $I2CX, PINS>
+                where
+                    B: WriteBuffer<Word = u8>,
+                {
+                    let (error, len) = {
+                        // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                        let (ptr, len) = unsafe { buffer.write_buffer() };
+
+                        if len == 0 {
+                            // the channel is never started and the bus never touched
+                            return DmaTransfer {
+                                transfer: Transfer::w(buffer, chan, self, 0),
+                                error: Some(Error::Length),
+                                stopped: false,
+                            };
+                        }
+
+                        chan.start_request::<$I2CX, signal::Rx, u8>(
+                            ptr as usize as u32,
+                            len,
+                            false,
+                            true,
+                            false,
+                        );
+
+                        if len == 1 {
+                            // LAST can't NACK a single byte: it's NACKed by clearing ACK and the
+                            // STOP is requested as soon as the address is acknowledged
+                            self.i2c.cr1.modify(|_, w| w.ack().clear_bit());
+                            self.i2c.cr2.modify(|_, w| w.dmaen().set_bit().last().clear_bit());
+                        } else {
+                            // LAST: NACK the byte that follows the DMA's end of transfer
+                            self.i2c.cr1.modify(|_, w| w.ack().set_bit());
+                            self.i2c.cr2.modify(|_, w| w.dmaen().set_bit().last().set_bit());
+                        }
+
+                        let error = previous
+                            .and_then(|_| {
+                                self.start_and_address(addr, true, start_timeout, addr_timeout)
+                            })
+                            .err();
+
+                        (error, len)
+                    };
+
+                    let stopped = len == 1 && error.is_none();
+                    if stopped {
+                        self.send_stop();
+                    }
+
+                    DmaTransfer {
+                        transfer: Transfer::w(buffer, chan, self, len),
+                        error,
+                        stopped,
+                    }
+                }
+
+                fn clear_errors(&mut self) {
+                    self.i2c.sr1.modify(|_, w| {
+                        w.berr()
+                            .clear_bit()
+                            .arlo()
+                            .clear_bit()
+                            .af()
+                            .clear_bit()
+                            .ovr()
+                            .clear_bit()
+                    });
+                }
+            }
+
+            // ends the transfer on the bus, unless the STOP has already been requested or the
+            // arbitration was lost, which leaves master mode
+            impl<PINS> TransferPayload for I2c<$I2CX, PINS> {
+                fn stop(&mut self) {
+                    self.i2c.cr2.modify(|_, w| w.dmaen().clear_bit().last().clear_bit());
+
+                    let stopping = self.i2c.cr1.read().stop().bit_is_set();
+                    if !stopping && self.i2c.sr2.read().msl().bit_is_set() {
+                        self.send_stop();
+                    }
+                }
+            }
+
+            impl<B, PINS> DmaTransfer<R, B, RequestChannel<$I2CX, signal::Tx>, $I2CX, PINS> {
+                /// Returns `Ok(true)` once all the bytes have been sent
+                pub fn is_done(&self) -> Result<bool, Error> {
+                    if let Some(error) = self.error {
+                        return Err(error);
+                    }
+                    let i2c = self.transfer.payload();
+                    i2c.check_errors()?;
+
+                    let done = self.transfer.is_done().map_err(|_| Error::Dma)?;
+
+                    Ok(done && (self.stopped || i2c.i2c.sr1.read().btf().bit_is_set()))
+                }
+
+                /// Waits for the transfer to end and sends a STOP condition
+                pub fn wait(
+                    self,
+                ) -> Result<
+                    (B, RequestChannel<$I2CX, signal::Tx>, I2c<$I2CX, PINS>),
+                    (Error, B, RequestChannel<$I2CX, signal::Tx>, I2c<$I2CX, PINS>),
+                > {
+                    let error = loop {
+                        match self.is_done() {
+                            Ok(true) => break None,
+                            Ok(false) => {}
+                            Err(error) => break Some(error),
+                        }
+                    };
+
+                    let (buffer, chan, mut i2c, _) = self.transfer.stop();
+                    if let Some(error) = error {
+                        i2c.clear_errors();
+                        Err((error, buffer, chan, i2c))
+                    } else {
+                        Ok((buffer, chan, i2c))
+                    }
+                }
+            }
+
+            impl<B, PINS> DmaTransfer<W, B, RequestChannel<$I2CX, signal::Rx>, $I2CX, PINS> {
+                /// Returns `Ok(true)` once all the bytes have been received
+                pub fn is_done(&self) -> Result<bool, Error> {
+                    if let Some(error) = self.error {
+                        return Err(error);
+                    }
+                    self.transfer.payload().check_errors()?;
+
+                    self.transfer.is_done().map_err(|_| Error::Dma)
+                }
+
+                /// Waits for the transfer to end and sends a STOP condition
+                pub fn wait(
+                    self,
+                ) -> Result<
+                    (B, RequestChannel<$I2CX, signal::Rx>, I2c<$I2CX, PINS>),
+                    (Error, B, RequestChannel<$I2CX, signal::Rx>, I2c<$I2CX, PINS>),
+                > {
+                    let error = loop {
+                        match self.is_done() {
+                            Ok(true) => break None,
+                            Ok(false) => {}
+                            Err(error) => break Some(error),
+                        }
+                    };
+
+                    let (buffer, chan, mut i2c, _) = self.transfer.stop();
+                    if let Some(error) = error {
+                        i2c.clear_errors();
+                        Err((error, buffer, chan, i2c))
+                    } else {
+                        Ok((buffer, chan, i2c))
+                    }
+                }
+            }
+        )+
+    }
+}
+
+dma! {
+    I2C1,
+    I2C2,
+}
@@ -29,15 +29,32 @@
 //!
 //! [cortex-m-quickstart]: https://docs.rs/cortex-m-quickstart/~0.2.3
 //!
+//! - Running the unit tests
+//!
+//! The logic that doesn't touch the hardware, like the LIN frames, is unit tested on the host.
+//! `.cargo/config` builds for `thumbv7m-none-eabi` by default so the host target has to be named:
+//!
+//! ``` text
+//! $ cargo test --target x86_64-unknown-linux-gnu --lib
+//! ```
+//!
 //! # Examples
 //!
 //! See the [examples] module.
 //!
 //! [examples]: examples/index.html
+//!
+//! # embedded-hal 1.0
+//!
+//! Enable the "embedded-hal-1" Cargo feature to also get implementations of the `embedded-hal`
+//! 1.0 `digital`, `spi`, `i2c`, `delay` and `pwm` traits. The 0.2 traits remain available and are
+//! the ones exported through the prelude.
 
 #![no_std]
 
 use embedded_hal as hal;
+#[cfg(feature = "embedded-hal-1")]
+use embedded_hal_1 as hal1;
 
 #[cfg(feature="stm32f103")]
 pub use stm32f103xx;
@@ -8,6 +8,12 @@ pub use crate::hal::digital::ToggleableOutputPin as _embedded_hal_digital_Toggle
 pub use crate::hal::prelude::*;
 pub use crate::pwm::PwmExt as _stm32f103xx_hal_pwm_PwmExt;
 pub use crate::rcc::RccExt as _stm32f103xx_hal_rcc_RccExt;
+pub use crate::serial::CircReadDma as _stm32f103xx_hal_serial_CircReadDma;
+pub use crate::serial::CircWriteDma as _stm32f103xx_hal_serial_CircWriteDma;
 pub use crate::serial::ReadDma as _stm32f103xx_hal_serial_ReadDma;
 pub use crate::serial::WriteDma as _stm32f103xx_hal_serial_WriteDma;
+pub use crate::spi::CircWriteDma as _stm32f103xx_hal_spi_CircWriteDma;
+pub use crate::spi::ReadDma as _stm32f103xx_hal_spi_ReadDma;
+pub use crate::spi::ReadWriteDma as _stm32f103xx_hal_spi_ReadWriteDma;
+pub use crate::spi::WriteDma as _stm32f103xx_hal_spi_WriteDma;
 pub use crate::time::U32Ext as _stm32f103xx_hal_time_U32Ext;
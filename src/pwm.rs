@@ -114,8 +114,7 @@ impl PwmExt for TIM2 {
         PINS: Pins<Self>,
         T: Into<Hertz>,
     {
-        mapr.mapr()
-            .modify(|_, w| unsafe { w.tim2_remap().bits(PINS::REMAP) });
+        mapr.modify_mapr(|_, w| unsafe { w.tim2_remap().bits(PINS::REMAP) });
 
         tim2(self, _pins, freq.into(), clocks, apb)
     }
@@ -134,8 +133,7 @@ impl PwmExt for TIM3 {
         PINS: Pins<Self>,
         T: Into<Hertz>,
     {
-        mapr.mapr()
-            .modify(|_, w| unsafe { w.tim3_remap().bits(PINS::REMAP) });
+        mapr.modify_mapr(|_, w| unsafe { w.tim3_remap().bits(PINS::REMAP) });
 
         tim3(self, _pins, freq.into(), clocks, apb)
     }
@@ -154,8 +152,7 @@ impl PwmExt for TIM4 {
         PINS: Pins<Self>,
         T: Into<Hertz>,
     {
-        mapr.mapr()
-            .modify(|_, w| w.tim4_remap().bit(PINS::REMAP == 1));
+        mapr.modify_mapr(|_, w| w.tim4_remap().bit(PINS::REMAP == 1));
 
         tim4(self, _pins, freq.into(), clocks, apb)
     }
@@ -171,6 +168,26 @@ pub struct C2;
 pub struct C3;
 pub struct C4;
 
+#[cfg(feature = "embedded-hal-1")]
+impl<TIM, CHANNEL> crate::hal1::pwm::ErrorType for Pwm<TIM, CHANNEL> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<TIM, CHANNEL> crate::hal1::pwm::SetDutyCycle for Pwm<TIM, CHANNEL>
+where
+    Pwm<TIM, CHANNEL>: crate::hal::PwmPin<Duty = u16>,
+{
+    fn max_duty_cycle(&self) -> u16 {
+        crate::hal::PwmPin::get_max_duty(self)
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        crate::hal::PwmPin::set_duty(self, duty);
+        Ok(())
+    }
+}
+
 macro_rules! hal {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident),)+) => {
         $(
@@ -35,8 +35,7 @@ impl<PINS> Qei<TIM2, PINS> {
     where
         PINS: Pins<TIM2>,
     {
-        mapr.mapr()
-            .modify(|_, w| unsafe { w.tim2_remap().bits(PINS::REMAP) });
+        mapr.modify_mapr(|_, w| unsafe { w.tim2_remap().bits(PINS::REMAP) });
 
         Qei::_tim2(tim, pins, apb)
     }
@@ -47,8 +46,7 @@ impl<PINS> Qei<TIM3, PINS> {
     where
         PINS: Pins<TIM3>,
     {
-        mapr.mapr()
-            .modify(|_, w| unsafe { w.tim3_remap().bits(PINS::REMAP) });
+        mapr.modify_mapr(|_, w| unsafe { w.tim3_remap().bits(PINS::REMAP) });
 
         Qei::_tim3(tim, pins, apb)
     }
@@ -59,8 +57,7 @@ impl<PINS> Qei<TIM4, PINS> {
     where
         PINS: Pins<TIM4>,
     {
-        mapr.mapr()
-            .modify(|_, w| w.tim4_remap().bit(PINS::REMAP == 1));
+        mapr.modify_mapr(|_, w| w.tim4_remap().bit(PINS::REMAP == 1));
 
         Qei::_tim4(tim, pins, apb)
     }
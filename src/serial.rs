@@ -1,19 +1,28 @@
+use core::fmt;
 use core::marker::PhantomData;
 use core::ptr;
-use core::sync::atomic::{self, Ordering};
 
-use cast::u16;
 use nb;
-use crate::device::{USART1, USART2, USART3};
+use crate::device::{UART4, UART5, USART1, USART2, USART3};
 use void::Void;
 
 use crate::afio::MAPR;
-use crate::dma::{dma1, CircBuffer, Static, Transfer, R, W};
-use crate::gpio::gpioa::{PA10, PA2, PA3, PA9};
-use crate::gpio::gpiob::{PB10, PB11, PB6, PB7};
-use crate::gpio::{Alternate, Floating, Input, PushPull};
+use crate::bb;
+use crate::dma::{
+    signal, CircBuffer, CircWriteBuffer, DmaChannel, ReadBuffer, Request, RequestChannel,
+    RingBuffer, Transfer, TransferPayload, WriteBuffer, R, W,
+};
+use crate::gpio::gpioa::{PA0, PA1, PA10, PA11, PA12, PA2, PA3, PA9};
+use crate::gpio::gpiob::{PB10, PB11, PB13, PB14, PB6, PB7};
+use crate::gpio::gpioc::{PC10, PC11, PC12};
+use crate::gpio::gpiod::{PD11, PD12, PD2, PD3, PD4, PD5, PD6, PD8, PD9};
+use crate::gpio::{Alternate, Floating, Input, OpenDrain, PushPull};
 use crate::rcc::{APB1, APB2, Clocks};
-use crate::time::Bps;
+use crate::time::{Bps, Hertz};
+
+pub mod buffered;
+pub mod lin;
+pub mod rs485;
 
 /// Interrupt event
 pub enum Event {
@@ -21,6 +30,14 @@ pub enum Event {
     Rxne,
     /// New data can be sent
     Txe,
+    /// The line went idle after receiving data
+    Idle,
+    /// The last frame has been completely sent
+    TransmissionComplete,
+    /// A parity error was detected
+    ParityError,
+    /// A LIN break was detected (LIN mode only)
+    LinBreak,
 }
 
 /// Serial error
@@ -38,8 +55,168 @@ pub enum Error {
     _Extensible,
 }
 
+/// Serial configuration that can't be applied
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvalidConfig {
+    /// The baud rate is zero or out of reach of the peripheral clock
+    BaudRate,
+    /// The achieved baud rate deviates more than `Config::max_baud_rate_error`
+    BaudRateError,
+    /// LIN mode needs 8N1 frames and separate TX and RX pins
+    Lin,
+    /// 0.5 and 1.5 stop bits are not available on UART4 and UART5
+    StopBits,
+    #[doc(hidden)]
+    _Extensible,
+}
+
+/// Number of bits per frame, not counting start and stop bits
+///
+/// The parity bit, when enabled, takes the place of the most significant data bit. `Rx::read`
+/// strips it from the received words; DMA receptions keep it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WordLength {
+    Bits8,
+    Bits9,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StopBits {
+    /// 1 stop bit
+    Stop1,
+    /// 0.5 stop bits
+    Stop0p5,
+    /// 2 stop bits
+    Stop2,
+    /// 1.5 stop bits
+    Stop1p5,
+}
+
+impl StopBits {
+    fn bits(self) -> u8 {
+        match self {
+            StopBits::Stop1 => 0b00,
+            StopBits::Stop0p5 => 0b01,
+            StopBits::Stop2 => 0b10,
+            StopBits::Stop1p5 => 0b11,
+        }
+    }
+}
+
+/// Length of the run of dominant bits that is detected as a LIN break
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinBreakDetection {
+    Bits10,
+    Bits11,
+}
+
+/// Serial configuration; defaults to 115200 bps 8N1
+#[derive(Clone, Copy)]
+pub struct Config {
+    pub baud_rate: Bps,
+    pub word_length: WordLength,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// Largest accepted deviation of the achieved baud rate, in parts per million
+    pub max_baud_rate_error: u32,
+    /// LIN mode, with the given break detection length; requires 8N1 frames
+    pub lin: Option<LinBreakDetection>,
+}
+
+impl Config {
+    pub fn baud_rate(mut self, baud_rate: Bps) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    pub fn word_length(mut self, word_length: WordLength) -> Self {
+        self.word_length = word_length;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn max_baud_rate_error(mut self, ppm: u32) -> Self {
+        self.max_baud_rate_error = ppm;
+        self
+    }
+
+    pub fn lin(mut self, break_detection: LinBreakDetection) -> Self {
+        self.lin = Some(break_detection);
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            baud_rate: Bps(115_200),
+            word_length: WordLength::Bits8,
+            parity: Parity::None,
+            stop_bits: StopBits::Stop1,
+            // 2%
+            max_baud_rate_error: 20_000,
+            lin: None,
+        }
+    }
+}
+
+/// Computes the BRR value for `baud_rate` from the USART kernel clock `pclk`
+///
+/// BRR holds USARTDIV = pclk / (16 * baud_rate) as a 12-bit mantissa and a 4-bit fraction, that
+/// is pclk / baud_rate in sixteenths; it's rounded to the nearest value.
+///
+/// Returns the BRR value, the achieved baud rate and its error in parts per million.
+pub fn brr(pclk: Hertz, baud_rate: Bps) -> (u32, Bps, u32) {
+    if baud_rate.0 == 0 {
+        return (0, Bps(0), 1_000_000);
+    }
+
+    let brr = (pclk.0 + baud_rate.0 / 2) / baud_rate.0;
+
+    if brr == 0 {
+        return (0, Bps(0), 1_000_000);
+    }
+
+    let actual = (pclk.0 + brr / 2) / brr;
+    let deviation = if actual > baud_rate.0 {
+        actual - baud_rate.0
+    } else {
+        baud_rate.0 - actual
+    };
+    let error = u64::from(deviation) * 1_000_000 / u64::from(baud_rate.0);
+
+    (brr, Bps(actual), error as u32)
+}
+
+/// TX and RX pins, optionally followed by the CTS and RTS pins of hardware flow control
+///
+/// A lone TX pin in alternate open-drain mode selects single-wire half-duplex: the receiver is
+/// turned off while transmitting and back on, once the transmission is complete, by the next read
+/// or flush. Flush after writing to resume a DMA reception.
 pub trait Pins<USART> {
     const REMAP: u8;
+    /// Transmission is paused while CTS is high
+    const CTS: bool = false;
+    /// RTS is driven high while the receive buffer is full
+    const RTS: bool = false;
+    /// A single open-drain TX pin carries both directions
+    const HALF_DUPLEX: bool = false;
 }
 
 impl Pins<USART1> for (PA9<Alternate<PushPull>>, PA10<Input<Floating>>) {
@@ -50,30 +227,184 @@ impl Pins<USART1> for (PB6<Alternate<PushPull>>, PB7<Input<Floating>>) {
     const REMAP: u8 = 1;
 }
 
+impl Pins<USART1>
+    for (
+        PA9<Alternate<PushPull>>,
+        PA10<Input<Floating>>,
+        PA11<Input<Floating>>,
+        PA12<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 0;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+impl Pins<USART1>
+    for (
+        PB6<Alternate<PushPull>>,
+        PB7<Input<Floating>>,
+        PA11<Input<Floating>>,
+        PA12<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 1;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
 impl Pins<USART2> for (PA2<Alternate<PushPull>>, PA3<Input<Floating>>) {
     const REMAP: u8 = 0;
 }
 
-// impl Pins<USART2> for (PD5<Alternate<PushPull>>, PD6<Input<Floating>>) {
-//     const REMAP: u8 = 0;
-// }
+impl Pins<USART2>
+    for (
+        PA2<Alternate<PushPull>>,
+        PA3<Input<Floating>>,
+        PA0<Input<Floating>>,
+        PA1<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 0;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+impl Pins<USART2>
+    for (
+        PD5<Alternate<PushPull>>,
+        PD6<Input<Floating>>,
+        PD3<Input<Floating>>,
+        PD4<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 1;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+impl Pins<USART2> for (PD5<Alternate<PushPull>>, PD6<Input<Floating>>) {
+    const REMAP: u8 = 1;
+}
 
 impl Pins<USART3> for (PB10<Alternate<PushPull>>, PB11<Input<Floating>>) {
     const REMAP: u8 = 0;
 }
 
-// impl Pins<USART3> for (PC10<Alternate<PushPull>>, PC11<Input<Floating>>) {
-//     const REMAP: u8 = 1;
-// }
+impl Pins<USART3>
+    for (
+        PB10<Alternate<PushPull>>,
+        PB11<Input<Floating>>,
+        PB13<Input<Floating>>,
+        PB14<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 0;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+/// Partial remap
+impl Pins<USART3> for (PC10<Alternate<PushPull>>, PC11<Input<Floating>>) {
+    const REMAP: u8 = 0b01;
+}
+
+/// Partial remap
+impl Pins<USART3>
+    for (
+        PC10<Alternate<PushPull>>,
+        PC11<Input<Floating>>,
+        PB13<Input<Floating>>,
+        PB14<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 0b01;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+/// Full remap
+impl Pins<USART3> for (PD8<Alternate<PushPull>>, PD9<Input<Floating>>) {
+    const REMAP: u8 = 0b11;
+}
+
+/// Full remap
+impl Pins<USART3>
+    for (
+        PD8<Alternate<PushPull>>,
+        PD9<Input<Floating>>,
+        PD11<Input<Floating>>,
+        PD12<Alternate<PushPull>>,
+    )
+{
+    const REMAP: u8 = 0b11;
+    const CTS: bool = true;
+    const RTS: bool = true;
+}
+
+/// UART4 and UART5 can't be remapped
+impl Pins<UART4> for (PC10<Alternate<PushPull>>, PC11<Input<Floating>>) {
+    const REMAP: u8 = 0;
+}
+
+impl Pins<UART5> for (PC12<Alternate<PushPull>>, PD2<Input<Floating>>) {
+    const REMAP: u8 = 0;
+}
+
+/// Single-wire half-duplex; the line needs a pull-up
+impl Pins<USART1> for PA9<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0;
+    const HALF_DUPLEX: bool = true;
+}
 
-// impl Pins<USART3> for (PD8<Alternate<PushPull>>, PD9<Input<Floating>>) {
-//     const REMAP: u8 = 0b11;
-// }
+impl Pins<USART1> for PB6<Alternate<OpenDrain>> {
+    const REMAP: u8 = 1;
+    const HALF_DUPLEX: bool = true;
+}
+
+impl Pins<USART2> for PA2<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0;
+    const HALF_DUPLEX: bool = true;
+}
+
+impl Pins<USART2> for PD5<Alternate<OpenDrain>> {
+    const REMAP: u8 = 1;
+    const HALF_DUPLEX: bool = true;
+}
+
+impl Pins<USART3> for PB10<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0;
+    const HALF_DUPLEX: bool = true;
+}
+
+/// Partial remap
+impl Pins<USART3> for PC10<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0b01;
+    const HALF_DUPLEX: bool = true;
+}
+
+/// Full remap
+impl Pins<USART3> for PD8<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0b11;
+    const HALF_DUPLEX: bool = true;
+}
+
+impl Pins<UART4> for PC10<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0;
+    const HALF_DUPLEX: bool = true;
+}
+
+impl Pins<UART5> for PC12<Alternate<OpenDrain>> {
+    const REMAP: u8 = 0;
+    const HALF_DUPLEX: bool = true;
+}
 
 /// Serial abstraction
 pub struct Serial<USART, PINS> {
     usart: USART,
     pins: PINS,
+    baud_rate: Bps,
+    baud_rate_error: u32,
 }
 
 /// Serial receiver
@@ -92,57 +423,132 @@ macro_rules! hal {
             $usartX:ident,
             $usartXen:ident,
             $usartXrst:ident,
-            $usartX_remap:ident,
-            $bit:ident,
-            $closure:expr,
-            $APB:ident
+            $remap:expr,
+            $APB:ident,
+            $pclkX:ident,
+            $dma:expr,
+            $half_stop_bits:expr
         ),
     )+) => {
         $(
             impl<PINS> Serial<$USARTX, PINS> {
+                /// Configures the serial interface
+                ///
+                /// Fails, before the peripheral is touched, if `config` can't be applied.
                 pub fn $usartX(
                     usart: $USARTX,
                     pins: PINS,
                     mapr: &mut MAPR,
-                    baud_rate: Bps,
+                    config: Config,
                     clocks: Clocks,
                     apb: &mut $APB,
-                ) -> Self
+                ) -> Result<Self, InvalidConfig>
                 where
                     PINS: Pins<$USARTX>,
                 {
+                    let (brr, baud_rate, baud_rate_error) =
+                        brr(clocks.$pclkX(), config.baud_rate);
+                    if brr < 16 || brr > 0xffff {
+                        return Err(InvalidConfig::BaudRate);
+                    }
+                    if baud_rate_error > config.max_baud_rate_error {
+                        return Err(InvalidConfig::BaudRateError);
+                    }
+
+                    if config.lin.is_some()
+                        && (config.word_length != WordLength::Bits8
+                            || config.parity != Parity::None
+                            || config.stop_bits != StopBits::Stop1
+                            || PINS::HALF_DUPLEX)
+                    {
+                        return Err(InvalidConfig::Lin);
+                    }
+
+                    if !$half_stop_bits
+                        && (config.stop_bits == StopBits::Stop0p5
+                            || config.stop_bits == StopBits::Stop1p5)
+                    {
+                        return Err(InvalidConfig::StopBits);
+                    }
+
                     // enable and reset $USARTX
                     apb.enr().modify(|_, w| w.$usartXen().enabled());
                     apb.rstr().modify(|_, w| w.$usartXrst().set_bit());
                     apb.rstr().modify(|_, w| w.$usartXrst().clear_bit());
 
-                    #[allow(unused_unsafe)]
-                    mapr.mapr()
-                        .modify(|_, w| unsafe{
-                            w.$usartX_remap().$bit(($closure)(PINS::REMAP))
-                        });
+                    ($remap)(mapr, PINS::REMAP);
+
+                    if $dma {
+                        // enable DMA transfers
+                        usart.cr3.write(|w| w.dmat().set_bit().dmar().set_bit());
+                    }
+
+                    // CTSE / RTSE: hardware flow control; it also paces the DMA transfers
+                    if PINS::CTS || PINS::RTS {
+                        usart.cr3.modify(|_, w| w.ctse().bit(PINS::CTS).rtse().bit(PINS::RTS));
+                    }
 
-                    // enable DMA transfers
-                    usart.cr3.write(|w| w.dmat().set_bit().dmar().set_bit());
+                    // HDSEL: single-wire half-duplex; the transmitter releases the line when idle
+                    if PINS::HALF_DUPLEX {
+                        usart.cr3.modify(|_, w| w.hdsel().set_bit());
+                    }
 
-                    let brr = clocks.pclk2().0 / baud_rate.0;
-                    assert!(brr >= 16, "impossible baud rate");
                     usart.brr.write(|w| unsafe { w.bits(brr) });
 
+                    // LINEN / LBDL: LIN mode and break detection length
+                    usart.cr2.write(|w| unsafe {
+                        w.stop()
+                            .bits(config.stop_bits.bits())
+                            .linen()
+                            .bit(config.lin.is_some())
+                            .lbdl()
+                            .bit(config.lin == Some(LinBreakDetection::Bits11))
+                    });
+
                     // UE: enable USART
                     // RE: enable receiver
                     // TE: enable transceiver
-                    usart
-                        .cr1
-                        .write(|w| w.ue().set_bit().re().set_bit().te().set_bit());
+                    // M: word length
+                    // PCE / PS: parity control / selection
+                    usart.cr1.write(|w| {
+                        w.ue()
+                            .set_bit()
+                            .re()
+                            .set_bit()
+                            .te()
+                            .set_bit()
+                            .m()
+                            .bit(config.word_length == WordLength::Bits9)
+                            .pce()
+                            .bit(config.parity != Parity::None)
+                            .ps()
+                            .bit(config.parity == Parity::Odd)
+                    });
 
-                    Serial { usart, pins }
+                    Ok(Serial { usart, pins, baud_rate, baud_rate_error })
+                }
+
+                /// Returns the baud rate actually achieved
+                pub fn baud_rate(&self) -> Bps {
+                    self.baud_rate
+                }
+
+                /// Returns the deviation of the achieved baud rate from the requested one, in
+                /// parts per million
+                pub fn baud_rate_error(&self) -> u32 {
+                    self.baud_rate_error
                 }
 
                 pub fn listen(&mut self, event: Event) {
                     match event {
                         Event::Rxne => self.usart.cr1.modify(|_, w| w.rxneie().set_bit()),
                         Event::Txe => self.usart.cr1.modify(|_, w| w.txeie().set_bit()),
+                        Event::Idle => self.usart.cr1.modify(|_, w| w.idleie().set_bit()),
+                        Event::TransmissionComplete => {
+                            self.usart.cr1.modify(|_, w| w.tcie().set_bit())
+                        }
+                        Event::ParityError => self.usart.cr1.modify(|_, w| w.peie().set_bit()),
+                        Event::LinBreak => self.usart.cr2.modify(|_, w| w.lbdie().set_bit()),
                     }
                 }
 
@@ -150,9 +556,42 @@ macro_rules! hal {
                     match event {
                         Event::Rxne => self.usart.cr1.modify(|_, w| w.rxneie().clear_bit()),
                         Event::Txe => self.usart.cr1.modify(|_, w| w.txeie().clear_bit()),
+                        Event::Idle => self.usart.cr1.modify(|_, w| w.idleie().clear_bit()),
+                        Event::TransmissionComplete => {
+                            self.usart.cr1.modify(|_, w| w.tcie().clear_bit())
+                        }
+                        Event::ParityError => self.usart.cr1.modify(|_, w| w.peie().clear_bit()),
+                        Event::LinBreak => self.usart.cr2.modify(|_, w| w.lbdie().clear_bit()),
                     }
                 }
 
+                /// Returns `true` if the line went idle after receiving data
+                pub fn is_idle(&self) -> bool {
+                    self.usart.sr.read().idle().bit_is_set()
+                }
+
+                /// Clears the idle flag, see `Rx::clear_idle_interrupt`
+                pub fn clear_idle_interrupt(&mut self) {
+                    let _ = self.usart.sr.read();
+                    let _ = self.usart.dr.read();
+                }
+
+                /// Sends a break once the current frame is out, see `Tx::send_break`
+                pub fn send_break(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.sbk().set_bit());
+                }
+
+                /// Returns `true` if a LIN break was detected
+                pub fn is_lin_break(&self) -> bool {
+                    self.usart.sr.read().lbd().bit_is_set()
+                }
+
+                /// Clears the LIN break flag
+                pub fn clear_lin_break(&mut self) {
+                    // see `Rx::clear_lin_break`
+                    self.usart.sr.write(|w| unsafe { w.bits(1 << 9 | 1 << 6 | 1 << 5) });
+                }
+
                 pub fn release(self) -> ($USARTX, PINS) {
                     (self.usart, self.pins)
                 }
@@ -169,10 +608,29 @@ macro_rules! hal {
                 }
             }
 
-            impl crate::hal::serial::Read<u8> for Rx<$USARTX> {
-                type Error = Error;
+            impl Rx<$USARTX> {
+                /// In half-duplex mode, turns the receiver back on once our own transmission is
+                /// over; returns `false` while it's still going on
+                fn unmute_receiver(&mut self) -> bool {
+                    // NOTE(unsafe) atomic reads with no side effects
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    if usart.cr1.read().re().bit_is_set() {
+                        true
+                    } else if usart.sr.read().tc().bit_is_set() {
+                        // NOTE(unsafe) atomic write through the bit band region
+                        unsafe { bb::set(&usart.cr1, 2) }
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                fn read_dr(&mut self) -> nb::Result<u16, Error> {
+                    if !self.unmute_receiver() {
+                        return Err(nb::Error::WouldBlock);
+                    }
 
-                fn read(&mut self) -> nb::Result<u8, Error> {
                     // NOTE(unsafe) atomic read with no side effects
                     let sr = unsafe { (*$USARTX::ptr()).sr.read() };
 
@@ -200,167 +658,197 @@ macro_rules! hal {
                         }
                         Err(nb::Error::Other(err))
                     } else {
-                        // Check if a byte is available
+                        // Check if a word is available
                         if sr.rxne().bit_is_set() {
-                            // Read the received byte
-                            // NOTE(read_volatile) see `write_volatile` below
-                            Ok(unsafe {
-                                ptr::read_volatile(&(*$USARTX::ptr()).dr as *const _ as *const _)
-                            })
+                            // Read the received word
+                            // NOTE(unsafe) atomic read of the RX buffer, which clears RXNE
+                            let word = unsafe { (*$USARTX::ptr()).dr.read().dr().bits() };
+
+                            // the parity bit is received as the most significant bit of the word
+                            // NOTE(unsafe) atomic read with no side effects
+                            let cr1 = unsafe { (*$USARTX::ptr()).cr1.read() };
+                            let mask = match (cr1.pce().bit_is_set(), cr1.m().bit_is_set()) {
+                                (true, false) => 0x7f,
+                                (true, true) => 0xff,
+                                (false, _) => 0x1ff,
+                            };
+
+                            Ok(word & mask)
                         } else {
                             Err(nb::Error::WouldBlock)
                         }
                     }
                 }
-            }
 
-            impl<B> ReadDma<B> for Rx<$USARTX> where B: AsMut<[u8]> {
-                fn circ_read(self, mut chan: Self::Dma, buffer: &'static mut [B; 2],
-                ) -> CircBuffer<B, Self::Dma>
-                {
-                    {
-                        let buffer = buffer[0].as_mut();
-                        chan.cmar().write(|w| unsafe {
-                            w.ma().bits(buffer.as_ptr() as usize as u32)
-                        });
-                        chan.cndtr().write(|w| unsafe{
-                            w.ndt().bits(u16(buffer.len() * 2).unwrap())
-                        });
-                        chan.cpar().write(|w| unsafe {
-                            w.pa().bits(&(*$USARTX::ptr()).dr as *const _ as usize as u32)
-                        });
-
-                        // TODO can we weaken this compiler barrier?
-                        // NOTE(compiler_fence) operations on `buffer` should not be reordered after
-                        // the next statement, which starts the DMA transfer
-                        atomic::compiler_fence(Ordering::SeqCst);
-
-                        chan.ccr().modify(|_, w| {
-                            w.mem2mem()
-                                .clear_bit()
-                                .pl()
-                                .medium()
-                                .msize()
-                                .bit8()
-                                .psize()
-                                .bit8()
-                                .minc()
-                                .set_bit()
-                                .pinc()
-                                .clear_bit()
-                                .circ()
-                                .set_bit()
-                                .dir()
-                                .clear_bit()
-                                .en()
-                                .set_bit()
-                        });
+                /// Returns `true` if the line went idle after receiving data
+                ///
+                /// With a circular DMA reception this marks the end of a variable-length frame.
+                pub fn is_idle(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*$USARTX::ptr()).sr.read().idle().bit_is_set() }
+                }
+
+                /// Clears the idle flag
+                ///
+                /// The flag is cleared by reading SR and then DR. Call this once the DMA (or
+                /// `read`) has taken the last received byte: a byte that arrives in between
+                /// would be lost.
+                pub fn clear_idle_interrupt(&mut self) {
+                    // NOTE(unsafe) atomic reads; the DR read only discards an already read byte
+                    unsafe {
+                        ptr::read_volatile(&(*$USARTX::ptr()).sr as *const _ as *const u32);
+                        ptr::read_volatile(&(*$USARTX::ptr()).dr as *const _ as *const u32);
                     }
+                }
 
-                    CircBuffer::new(buffer, chan)
+                /// Returns `true` if a LIN break was detected
+                pub fn is_lin_break(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*$USARTX::ptr()).sr.read().lbd().bit_is_set() }
                 }
 
-                fn read_exact(self, mut chan: Self::Dma, buffer: &'static mut B,
-                ) -> Transfer<W, &'static mut B, Self::Dma, Self>
-                {
-                    {
-                        let buffer = buffer.as_mut();
-                        chan.cmar().write(|w| unsafe {
-                            w.ma().bits(buffer.as_ptr() as usize as u32)
-                        });
-                        chan.cndtr().write(|w| unsafe{
-                            w.ndt().bits(u16(buffer.len()).unwrap())
-                        });
-                        chan.cpar().write(|w| unsafe {
-                            w.pa().bits(&(*$USARTX::ptr()).dr as *const _ as usize as u32)
-                        });
-
-                        // TODO can we weaken this compiler barrier?
-                        // NOTE(compiler_fence) operations on `buffer` should not be reordered after
-                        // the next statement, which starts the DMA transfer
-                        atomic::compiler_fence(Ordering::SeqCst);
-
-                        chan.ccr().modify(|_, w| {
-                            w.mem2mem()
-                                .clear_bit()
-                                .pl()
-                                .medium()
-                                .msize()
-                                .bit8()
-                                .psize()
-                                .bit8()
-                                .minc()
-                                .set_bit()
-                                .pinc()
-                                .clear_bit()
-                                .circ()
-                                .clear_bit()
-                                .dir()
-                                .clear_bit()
-                                .en()
-                                .set_bit()
-                        });
+                /// Clears the LIN break flag
+                pub fn clear_lin_break(&mut self) {
+                    // LBD is cleared by writing zero to it; write ones to the other rc_w0 flags
+                    // (CTS, TC and RXNE) so they are left alone
+                    // NOTE(unsafe) atomic write; the remaining bits are read-only
+                    unsafe {
+                        (*$USARTX::ptr())
+                            .sr
+                            .write(|w| w.bits(1 << 9 | 1 << 6 | 1 << 5))
                     }
+                }
+
+                /// Starts listening for the `Rxne` event
+                pub fn listen(&mut self) {
+                    // NOTE(unsafe) atomic write through the bit band region
+                    unsafe { bb::set(&(*$USARTX::ptr()).cr1, 5) }
+                }
 
-                    Transfer::w(buffer, chan, self)
+                /// Stops listening for the `Rxne` event
+                pub fn unlisten(&mut self) {
+                    // NOTE(unsafe) atomic write through the bit band region
+                    unsafe { bb::clear(&(*$USARTX::ptr()).cr1, 5) }
                 }
             }
 
-            impl<A, B> WriteDma<A, B> for Tx<$USARTX> where A: AsRef<[u8]>, B: Static<A> {
-                fn write_all(self, mut chan: Self::Dma, buffer: B
-                ) -> Transfer<R, B, Self::Dma, Self>
-                {
-                    {
-                        let buffer = buffer.borrow().as_ref();
-                        chan.cmar().write(|w| unsafe {
-                            w.ma().bits(buffer.as_ptr() as usize as u32)
-                        });
-                        chan.cndtr().write(|w| unsafe{
-                            w.ndt().bits(u16(buffer.len()).unwrap())
-                        });
-                        chan.cpar().write(|w| unsafe {
-                            w.pa().bits(&(*$USARTX::ptr()).dr as *const _ as usize as u32)
-                        });
-
-                        // TODO can we weaken this compiler barrier?
-                        // NOTE(compiler_fence) operations on `buffer` should not be reordered after
-                        // the next statement, which starts the DMA transfer
-                        atomic::compiler_fence(Ordering::SeqCst);
-
-                        chan.ccr().modify(|_, w| {
-                            w.mem2mem()
-                                .clear_bit()
-                                .pl()
-                                .medium()
-                                .msize()
-                                .bit8()
-                                .psize()
-                                .bit8()
-                                .minc()
-                                .set_bit()
-                                .pinc()
-                                .clear_bit()
-                                .circ()
-                                .clear_bit()
-                                .dir()
-                                .set_bit()
-                                .en()
-                                .set_bit()
-                        });
+            impl crate::hal::serial::Read<u8> for Rx<$USARTX> {
+                type Error = Error;
+
+                fn read(&mut self) -> nb::Result<u8, Error> {
+                    self.read_dr().map(|word| word as u8)
+                }
+            }
+
+            /// Reads whole frames, e.g. 9-bit words with `WordLength::Bits9`
+            impl crate::hal::serial::Read<u16> for Rx<$USARTX> {
+                type Error = Error;
+
+                fn read(&mut self) -> nb::Result<u16, Error> {
+                    self.read_dr()
+                }
+            }
+
+            impl Tx<$USARTX> {
+                /// In half-duplex mode, keeps the receiver from hearing our own transmission
+                fn mute_receiver(&mut self) {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    if usart.cr3.read().hdsel().bit_is_set() {
+                        // NOTE(unsafe) atomic write through the bit band region
+                        unsafe { bb::clear(&usart.cr1, 2) }
+                    }
+                }
+
+                /// Clears TC before a DMA transmission
+                ///
+                /// Writes to DR made by the DMA don't clear TC, which would still report the end of
+                /// the previous transmission and, in half-duplex mode, turn the receiver back on
+                /// while our own bytes are on the wire.
+                fn clear_tc(&mut self) {
+                    // write zero to TC and ones to the other rc_w0 flags (CTS, LBD and RXNE)
+                    // NOTE(unsafe) atomic write; the remaining bits are read-only
+                    unsafe {
+                        (*$USARTX::ptr())
+                            .sr
+                            .write(|w| w.bits(1 << 9 | 1 << 8 | 1 << 5))
+                    }
+                }
+
+                /// In half-duplex mode, turns the receiver back on; the transmission must be over
+                ///
+                /// `Rx` does this on its own when it's read, but a DMA reception never reads
+                /// through `Rx`.
+                fn unmute_receiver(&mut self) {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    if usart.cr3.read().hdsel().bit_is_set() {
+                        // NOTE(unsafe) atomic write through the bit band region
+                        unsafe { bb::set(&usart.cr1, 2) }
+                    }
+                }
+
+                /// Sends a break, a frame of zeros, once the current frame is out
+                ///
+                /// In LIN mode the break is 13 bits long; it starts a LIN header.
+                pub fn send_break(&mut self) {
+                    // NOTE(unsafe) atomic write through the bit band region
+                    unsafe { bb::set(&(*$USARTX::ptr()).cr1, 0) }
+                }
+
+                /// Starts listening for the `Txe` event
+                pub fn listen(&mut self) {
+                    // NOTE(unsafe) atomic write through the bit band region
+                    unsafe { bb::set(&(*$USARTX::ptr()).cr1, 7) }
+                }
+
+                /// Stops listening for the `Txe` event
+                pub fn unlisten(&mut self) {
+                    // NOTE(unsafe) atomic write through the bit band region
+                    unsafe { bb::clear(&(*$USARTX::ptr()).cr1, 7) }
+                }
+
+                /// Sends all the bytes in `buffer` like `bwrite_all`, but sends every `\n` as
+                /// `\r\n`, as most terminals expect
+                ///
+                /// Returns once the last byte has been handed to the transmitter; use `bflush` to
+                /// wait until it is out on the line.
+                pub fn bwrite_all_crlf(&mut self, buffer: &[u8]) -> Result<(), Void> {
+                    for &byte in buffer {
+                        if byte == b'\n' {
+                            nb::block!(crate::hal::serial::Write::<u8>::write(self, b'\r'))?;
+                        }
+                        nb::block!(crate::hal::serial::Write::<u8>::write(self, byte))?;
                     }
 
-                    Transfer::r(buffer, chan, self)
+                    Ok(())
+                }
+            }
+
+            impl crate::hal::blocking::serial::write::Default<u8> for Tx<$USARTX> {}
+
+            impl fmt::Write for Tx<$USARTX> {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    let _ = crate::hal::blocking::serial::Write::bwrite_all(self, s.as_bytes());
+                    Ok(())
                 }
             }
 
             impl crate::hal::serial::Write<u8> for Tx<$USARTX> {
                 type Error = Void;
 
+                /// Waits until the last frame is out on the line
+                ///
+                /// In half-duplex mode this also hands the line back to the receiver, which is
+                /// needed to resume a DMA reception.
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
                     // NOTE(unsafe) atomic read with no side effects
                     let sr = unsafe { (*$USARTX::ptr()).sr.read() };
 
                     if sr.tc().bit_is_set() {
+                        self.unmute_receiver();
                         Ok(())
                     } else {
                         Err(nb::Error::WouldBlock)
@@ -372,6 +860,8 @@ macro_rules! hal {
                     let sr = unsafe { (*$USARTX::ptr()).sr.read() };
 
                     if sr.txe().bit_is_set() {
+                        self.mute_receiver();
+
                         // NOTE(unsafe) atomic write to stateless register
                         // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
                         unsafe {
@@ -383,6 +873,30 @@ macro_rules! hal {
                     }
                 }
             }
+
+            /// Writes whole frames, e.g. 9-bit words with `WordLength::Bits9`
+            impl crate::hal::serial::Write<u16> for Tx<$USARTX> {
+                type Error = Void;
+
+                fn flush(&mut self) -> nb::Result<(), Self::Error> {
+                    crate::hal::serial::Write::<u8>::flush(self)
+                }
+
+                fn write(&mut self, word: u16) -> nb::Result<(), Self::Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let sr = unsafe { (*$USARTX::ptr()).sr.read() };
+
+                    if sr.txe().bit_is_set() {
+                        self.mute_receiver();
+
+                        // NOTE(unsafe) atomic write to stateless register
+                        unsafe { (*$USARTX::ptr()).dr.write(|w| w.dr().bits(word)) }
+                        Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+            }
         )+
     }
 }
@@ -392,75 +906,248 @@ hal! {
         usart1,
         usart1en,
         usart1rst,
-        usart1_remap,
-        bit,
-        |remap| remap == 1,
-        APB2
+        |mapr: &mut MAPR, remap| mapr.modify_mapr(|_, w| w.usart1_remap().bit(remap == 1)),
+        APB2,
+        pclk2,
+        true,
+        true
     ),
     USART2: (
         usart2,
         usart2en,
         usart2rst,
-        usart2_remap,
-        bit,
-        |remap| remap == 1,
-        APB1
+        |mapr: &mut MAPR, remap| mapr.modify_mapr(|_, w| w.usart2_remap().bit(remap == 1)),
+        APB1,
+        pclk1,
+        true,
+        true
     ),
     USART3: (
         usart3,
         usart3en,
         usart3rst,
-        usart3_remap,
-        bits,
-        |remap| remap,
-        APB1
+        |mapr: &mut MAPR, remap| mapr.modify_mapr(|_, w| unsafe { w.usart3_remap().bits(remap) }),
+        APB1,
+        pclk1,
+        true,
+        true
+    ),
+    // NOTE UART4 and UART5 don't support 0.5 and 1.5 stop bits
+    UART4: (
+        uart4,
+        uart4en,
+        uart4rst,
+        |_: &mut MAPR, _| {},
+        APB1,
+        pclk1,
+        true,
+        false
+    ),
+    // NOTE UART5 has no DMA requests
+    UART5: (
+        uart5,
+        uart5en,
+        uart5rst,
+        |_: &mut MAPR, _| {},
+        APB1,
+        pclk1,
+        false,
+        false
     ),
 }
 
-use crate::dma::DmaChannel;
+macro_rules! dma {
+    ($($USARTX:ident,)+) => {
+        $(
+            impl DmaChannel for Rx<$USARTX> {
+                type Dma = RequestChannel<$USARTX, signal::Rx>;
+            }
+
+            impl DmaChannel for Tx<$USARTX> {
+                type Dma = RequestChannel<$USARTX, signal::Tx>;
+            }
 
-impl DmaChannel for Rx<USART1> {
-    type Dma = dma1::C5;
-}
+            impl<B> CircReadDma<B> for Rx<$USARTX> where B: AsMut<[u8]> {
+                fn circ_read(mut self, chan: Self::Dma, buffer: &'static mut [B; 2],
+                ) -> CircBuffer<B, Self::Dma, Self>
+                {
+                    while !self.unmute_receiver() {}
 
-impl DmaChannel for Tx<USART1> {
-    type Dma = dma1::C4;
-}
+                    chan.circ_read_request::<$USARTX, signal::Rx, B, u8>(Request::new(), buffer)
+                        .with_payload(self)
+                }
+            }
 
-impl DmaChannel for Rx<USART2> {
-    type Dma = dma1::C6;
-}
+            // the DMA requests stay enabled; they are harmless once the channel is stopped
+            impl TransferPayload for Rx<$USARTX> {
+                fn stop(&mut self) {}
+            }
+
+            impl<B, CHANNEL> CircBuffer<B, CHANNEL, Rx<$USARTX>> {
+                /// Returns `true` if the line went idle after receiving data, see `Rx::is_idle`
+                pub fn is_idle(&self) -> bool {
+                    self.payload().is_idle()
+                }
+
+                /// Clears the idle flag, see `Rx::clear_idle_interrupt`
+                pub fn clear_idle_interrupt(&mut self) {
+                    self.payload_mut().clear_idle_interrupt()
+                }
+            }
+
+            impl<B, CHANNEL> RingBuffer<B, CHANNEL, Rx<$USARTX>> {
+                /// Returns `true` if the line went idle after receiving data, see `Rx::is_idle`
+                pub fn is_idle(&self) -> bool {
+                    self.payload().is_idle()
+                }
+
+                /// Clears the idle flag, see `Rx::clear_idle_interrupt`
+                pub fn clear_idle_interrupt(&mut self) {
+                    self.payload_mut().clear_idle_interrupt()
+                }
+            }
+
+            impl<B> ReadDma<B> for Rx<$USARTX> where B: WriteBuffer<Word = u8> {
+                fn read_exact(mut self, mut chan: Self::Dma, mut buffer: B,
+                ) -> Transfer<W, B, Self::Dma, Self>
+                {
+                    while !self.unmute_receiver() {}
+
+                    // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                    let (ptr, len) = unsafe { buffer.write_buffer() };
+                    chan.start_request::<$USARTX, signal::Rx, u8>(
+                        ptr as usize as u32,
+                        len,
+                        false,
+                        true,
+                        false,
+                    );
+
+                    Transfer::w(buffer, chan, self, len)
+                }
+            }
 
-impl DmaChannel for Tx<USART2> {
-    type Dma = dma1::C7;
+            impl<B> CircWriteDma<B> for Tx<$USARTX> where B: AsMut<[u8]> {
+                fn circ_write(mut self, chan: Self::Dma, buffer: &'static mut [B; 2],
+                ) -> CircWriteBuffer<B, Self::Dma, Self>
+                {
+                    self.mute_receiver();
+                    self.clear_tc();
+
+                    chan.circ_write_request::<$USARTX, signal::Tx, B, u8>(Request::new(), buffer)
+                        .with_payload(self)
+                }
+            }
+
+            // the DMA requests stay enabled; they are harmless once the channel is stopped
+            impl TransferPayload for Tx<$USARTX> {
+                fn stop(&mut self) {}
+            }
+
+            impl<B> WriteDma<B> for Tx<$USARTX> where B: ReadBuffer<Word = u8> {
+                fn write_all(mut self, mut chan: Self::Dma, buffer: B
+                ) -> Transfer<R, B, Self::Dma, Self>
+                {
+                    self.mute_receiver();
+
+                    // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                    let (ptr, len) = unsafe { buffer.read_buffer() };
+                    // with nothing to send TC wouldn't be set again
+                    if len != 0 {
+                        self.clear_tc();
+                    }
+                    chan.start_request::<$USARTX, signal::Tx, u8>(
+                        ptr as usize as u32,
+                        len,
+                        true,
+                        true,
+                        false,
+                    );
+
+                    Transfer::r(buffer, chan, self, len)
+                }
+            }
+        )+
+    }
 }
 
-impl DmaChannel for Rx<USART3> {
-    type Dma = dma1::C3;
+dma! {
+    USART1,
+    USART2,
+    USART3,
+    UART4,
 }
 
-impl DmaChannel for Tx<USART3> {
-    type Dma = dma1::C2;
+pub trait CircReadDma<B>: DmaChannel
+where
+    B: AsMut<[u8]>,
+    Self: core::marker::Sized,
+{
+    /// Starts a circular reception into the two halves of `buffer`
+    ///
+    /// The receiver is kept by the returned buffer, which can still watch the line, e.g. with
+    /// `is_idle` to find the end of a variable-length frame. `stop` gives it back.
+    fn circ_read(self, chan: Self::Dma, buffer: &'static mut [B; 2])
+        -> CircBuffer<B, Self::Dma, Self>;
 }
 
 pub trait ReadDma<B>: DmaChannel
+where
+    B: WriteBuffer<Word = u8>,
+    Self: core::marker::Sized,
+{
+    fn read_exact(self, chan: Self::Dma, buffer: B) -> Transfer<W, B, Self::Dma, Self>;
+}
+
+pub trait CircWriteDma<B>: DmaChannel
 where
     B: AsMut<[u8]>,
     Self: core::marker::Sized,
 {
-    fn circ_read(self, chan: Self::Dma, buffer: &'static mut [B; 2]) -> CircBuffer<B, Self::Dma>;
-    fn read_exact(
+    /// Keeps sending the two halves of `buffer`, which should be filled beforehand
+    ///
+    /// `CircWriteBuffer::stop` gives back the transmitter.
+    fn circ_write(
         self,
         chan: Self::Dma,
-        buffer: &'static mut B,
-    ) -> Transfer<W, &'static mut B, Self::Dma, Self>;
+        buffer: &'static mut [B; 2],
+    ) -> CircWriteBuffer<B, Self::Dma, Self>;
 }
 
-pub trait WriteDma<A, B>: DmaChannel
+pub trait WriteDma<B>: DmaChannel
 where
-    A: AsRef<[u8]>,
-    B: Static<A>,
+    B: ReadBuffer<Word = u8>,
     Self: core::marker::Sized,
 {
     fn write_all(self, chan: Self::Dma, buffer: B) -> Transfer<R, B, Self::Dma, Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::time::{Bps, Hertz};
+
+    // BRR, achieved baud rate and error in ppm
+    fn brr(pclk: u32, baud_rate: u32) -> (u32, u32, u32) {
+        let (brr, actual, error) = super::brr(Hertz(pclk), Bps(baud_rate));
+        (brr, actual.0, error)
+    }
+
+    #[test]
+    fn exact_baud_rates() {
+        // USARTDIV = 39.0625
+        assert_eq!(brr(72_000_000, 115_200), (625, 115_200, 0));
+        assert_eq!(brr(36_000_000, 9_600), (3_750, 9_600, 0));
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_sixteenth() {
+        // 8 MHz / 115200 = 69.44 sixteenths; 8 MHz / 69 = 115942 bps
+        assert_eq!(brr(8_000_000, 115_200), (69, 115_942, 6_440));
+    }
+
+    #[test]
+    fn baud_rates_that_cant_be_reached() {
+        assert_eq!(brr(8_000_000, 0), (0, 0, 1_000_000));
+        assert_eq!(brr(8_000_000, 20_000_000), (0, 0, 1_000_000));
+    }
+}
@@ -0,0 +1,302 @@
+//! Interrupt driven serial interface with software queues
+//!
+//! `Serial::buffered` splits a serial interface into a `Buffered` end, used by the application to
+//! read and write without blocking, and a `Handler` end, whose `on_interrupt` method must be
+//! called from the `USARTx` interrupt handler. The two ends exchange bytes through a pair of
+//! single producer single consumer `Queue`s that don't need critical sections.
+//!
+//! The `Handler` must run at a higher priority than the code using the `Buffered` end.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use nb;
+use void::Void;
+
+use crate::device::{UART4, UART5, USART1, USART2, USART3};
+use crate::hal::serial::{Read, Write};
+
+use super::{Error, Event, Rx, Serial, Tx};
+
+/// Fixed capacity byte queue shared by the application and the interrupt handler
+///
+/// A queue built on a buffer of `N` bytes holds up to `N - 1` bytes.
+pub struct Queue<B> {
+    buffer: B,
+    // index of the next byte to dequeue; only written by the consumer
+    head: AtomicUsize,
+    // index of the next free slot; only written by the producer
+    tail: AtomicUsize,
+    overruns: AtomicUsize,
+    errors: AtomicUsize,
+}
+
+impl<B> Queue<B> {
+    /// Creates an empty queue on top of `buffer`
+    ///
+    /// `buffer` must be at least 2 bytes long. A shorter one gives a queue that holds no byte:
+    /// every byte written to it is rejected and every byte received is counted as an overrun.
+    pub const fn new(buffer: B) -> Self {
+        Queue {
+            buffer,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overruns: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<B> Queue<B>
+where
+    B: AsMut<[u8]>,
+{
+    fn split(&'static mut self) -> (Producer, Consumer) {
+        let buffer = self.buffer.as_mut();
+        let ptr = buffer.as_mut_ptr();
+        let capacity = buffer.len();
+
+        // the indices and the counters are only moved by the two ends so start from a clean state
+        self.head.store(0, Ordering::Relaxed);
+        self.tail.store(0, Ordering::Relaxed);
+        self.overruns.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+
+        let state = &*self;
+        (
+            Producer {
+                buffer: ptr,
+                capacity,
+                head: &state.head,
+                tail: &state.tail,
+                overruns: &state.overruns,
+                errors: &state.errors,
+            },
+            Consumer {
+                buffer: ptr,
+                capacity,
+                head: &state.head,
+                tail: &state.tail,
+                overruns: &state.overruns,
+                errors: &state.errors,
+            },
+        )
+    }
+}
+
+struct Producer {
+    buffer: *mut u8,
+    capacity: usize,
+    head: &'static AtomicUsize,
+    tail: &'static AtomicUsize,
+    overruns: &'static AtomicUsize,
+    errors: &'static AtomicUsize,
+}
+
+// NOTE(unsafe) the producer is the only one writing `tail` and the slot it points to
+unsafe impl Send for Producer {}
+
+impl Producer {
+    fn enqueue(&mut self, byte: u8) -> Result<(), u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        // `>=`: an empty buffer is always full, rather than written out of bounds
+        let next = if tail + 1 >= self.capacity { 0 } else { tail + 1 };
+
+        if next == self.head.load(Ordering::Acquire) {
+            Err(byte)
+        } else {
+            // NOTE(unsafe) the consumer doesn't access this slot until `tail` moves past it
+            unsafe { self.buffer.add(tail).write_volatile(byte) }
+            self.tail.store(next, Ordering::Release);
+            Ok(())
+        }
+    }
+}
+
+struct Consumer {
+    buffer: *mut u8,
+    capacity: usize,
+    head: &'static AtomicUsize,
+    tail: &'static AtomicUsize,
+    overruns: &'static AtomicUsize,
+    errors: &'static AtomicUsize,
+}
+
+// NOTE(unsafe) the consumer is the only one writing `head` and reading the slot it points to
+unsafe impl Send for Consumer {}
+
+impl Consumer {
+    fn peek(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+
+        if head == self.tail.load(Ordering::Acquire) {
+            None
+        } else {
+            // NOTE(unsafe) the producer doesn't touch this slot until `head` moves past it
+            Some(unsafe { self.buffer.add(head).read_volatile() })
+        }
+    }
+
+    fn dequeue(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+
+        let head = self.head.load(Ordering::Relaxed);
+        let next = if head + 1 == self.capacity { 0 } else { head + 1 };
+        self.head.store(next, Ordering::Release);
+
+        Some(byte)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Relaxed) == self.tail.load(Ordering::Acquire)
+    }
+}
+
+/// Application end of a buffered serial interface
+pub struct Buffered<USART> {
+    rx: Consumer,
+    tx: Producer,
+    usart_tx: Tx<USART>,
+}
+
+/// Interrupt end of a buffered serial interface
+pub struct Handler<USART> {
+    rx: Producer,
+    tx: Consumer,
+    usart_rx: Rx<USART>,
+    usart_tx: Tx<USART>,
+}
+
+impl<USART> Buffered<USART> {
+    /// Number of received bytes that were lost because the receive queue or the data register
+    /// was full
+    pub fn overruns(&self) -> usize {
+        self.rx.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Number of received bytes that were discarded because of a framing, noise or parity error
+    pub fn errors(&self) -> usize {
+        self.rx.errors.load(Ordering::Relaxed)
+    }
+}
+
+macro_rules! buffered {
+    ($($USARTX:ident,)+) => {
+        $(
+            impl<PINS> Serial<$USARTX, PINS> {
+                /// Turns this serial interface into an interrupt driven one that uses `rx` and
+                /// `tx` as receive and transmit queues
+                pub fn buffered<RB, TB>(
+                    mut self,
+                    rx: &'static mut Queue<RB>,
+                    tx: &'static mut Queue<TB>,
+                ) -> (Buffered<$USARTX>, Handler<$USARTX>)
+                where
+                    RB: AsMut<[u8]>,
+                    TB: AsMut<[u8]>,
+                {
+                    let (rx_producer, rx_consumer) = rx.split();
+                    let (tx_producer, tx_consumer) = tx.split();
+
+                    self.listen(Event::Rxne);
+                    let (usart_tx, usart_rx) = self.split();
+
+                    (
+                        Buffered {
+                            rx: rx_consumer,
+                            tx: tx_producer,
+                            usart_tx: Tx { _usart: PhantomData },
+                        },
+                        Handler {
+                            rx: rx_producer,
+                            tx: tx_consumer,
+                            usart_rx,
+                            usart_tx,
+                        },
+                    )
+                }
+            }
+
+            impl Handler<$USARTX> {
+                /// Moves received bytes into the receive queue and queued bytes into the
+                /// transmitter
+                ///
+                /// Call this from the interrupt handler of the serial interface.
+                pub fn on_interrupt(&mut self) {
+                    match Read::<u8>::read(&mut self.usart_rx) {
+                        Ok(byte) => {
+                            if self.rx.enqueue(byte).is_err() {
+                                self.rx.overruns.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                        Err(nb::Error::Other(Error::Overrun)) => {
+                            self.rx.overruns.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(nb::Error::Other(_)) => {
+                            self.rx.errors.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(nb::Error::WouldBlock) => {}
+                    }
+
+                    match self.tx.peek() {
+                        Some(byte) => {
+                            if Write::<u8>::write(&mut self.usart_tx, byte).is_ok() {
+                                self.tx.dequeue();
+                            }
+                        }
+                        None => self.usart_tx.unlisten(),
+                    }
+                }
+            }
+
+            impl Read<u8> for Buffered<$USARTX> {
+                type Error = Void;
+
+                fn read(&mut self) -> nb::Result<u8, Void> {
+                    self.rx.dequeue().ok_or(nb::Error::WouldBlock)
+                }
+            }
+
+            impl Write<u8> for Buffered<$USARTX> {
+                type Error = Void;
+
+                /// Waits until the transmit queue has been drained and the last byte has been
+                /// sent
+                fn flush(&mut self) -> nb::Result<(), Void> {
+                    if self.tx.is_empty() {
+                        Write::<u8>::flush(&mut self.usart_tx)
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                fn write(&mut self, byte: u8) -> nb::Result<(), Void> {
+                    self.tx.enqueue(byte).map_err(|_| nb::Error::WouldBlock)?;
+
+                    // wake up the handler, which stops listening once the queue is empty
+                    self.usart_tx.listen();
+                    Ok(())
+                }
+            }
+
+            /// Blocks while the transmit queue is full
+            impl fmt::Write for Buffered<$USARTX> {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    for byte in s.bytes() {
+                        let _ = nb::block!(self.write(byte));
+                    }
+                    Ok(())
+                }
+            }
+        )+
+    }
+}
+
+buffered! {
+    USART1,
+    USART2,
+    USART3,
+    UART4,
+    UART5,
+}
@@ -0,0 +1,406 @@
+//! LIN frames on top of a serial interface in LIN mode
+//!
+//! A LIN frame is a header, sent by the master, followed by a response, sent by the master or by
+//! a slave. The header is a break, the sync byte and the protected identifier (PID): the 6-bit
+//! frame identifier plus two parity bits. The response is 1 to 8 data bytes and a checksum.
+//!
+//! The frame logic (`protected_id`, `parse_protected_id` and `checksum`) is made of pure functions
+//! that don't touch the hardware. `Lin` uses them to exchange frames over a serial interface
+//! configured with `Config::lin`. It expects a LIN transceiver, which echoes everything sent on
+//! the bus back to the receiver: each sent byte is read back and compared to catch collisions.
+
+use nb;
+
+use crate::device::{UART4, UART5, USART1, USART2, USART3};
+use crate::hal::serial::{Read, Write};
+use crate::time::{Instant, MonoTimer};
+
+use super::{Rx, Tx};
+
+/// The sync byte that follows the break of a header
+pub const SYNC: u8 = 0x55;
+
+/// Largest number of data bytes in a response
+pub const MAX_DATA_LEN: usize = 8;
+
+/// Default time, in microseconds, a wait lasts before giving up, see `Lin::timeout`
+pub const DEFAULT_TIMEOUT_US: u32 = 100_000;
+
+/// Checksum model
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Checksum {
+    /// LIN 1.x: covers the data bytes only; also used by the diagnostic frames (0x3C and 0x3D)
+    Classic,
+    /// LIN 2.x: covers the protected identifier and the data bytes
+    Enhanced,
+}
+
+/// LIN error
+#[derive(Debug)]
+pub enum Error {
+    /// Error of the underlying serial interface
+    Serial(super::Error),
+    /// The byte after the break wasn't the sync byte
+    Sync,
+    /// The parity bits of the protected identifier are wrong
+    Parity,
+    /// The frame identifier doesn't fit in 6 bits
+    Id,
+    /// The response has more than `MAX_DATA_LEN` data bytes
+    Length,
+    /// The checksum of the response is wrong
+    Checksum,
+    /// A sent byte didn't come back as sent, e.g. because another node was sending too
+    Bit,
+    /// The break, a byte or its echo didn't arrive in time, e.g. because there's no transceiver
+    Timeout,
+    #[doc(hidden)]
+    _Extensible,
+}
+
+impl From<super::Error> for Error {
+    fn from(error: super::Error) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Computes the protected identifier of frame `id`, or `None` if `id` is greater than 0x3F
+///
+/// P0 = ID0 ^ ID1 ^ ID2 ^ ID4 is bit 6 and P1 = !(ID1 ^ ID3 ^ ID4 ^ ID5) is bit 7.
+pub fn protected_id(id: u8) -> Option<u8> {
+    if id > 0x3f {
+        return None;
+    }
+
+    let bit = |n: u8| (id >> n) & 1;
+
+    let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+    let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+
+    Some(id | p0 << 6 | p1 << 7)
+}
+
+/// Returns the frame identifier carried by `pid`, or `None` if its parity bits are wrong
+pub fn parse_protected_id(pid: u8) -> Option<u8> {
+    let id = pid & 0x3f;
+
+    if protected_id(id) == Some(pid) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Computes the checksum of a response: the inverted 8-bit sum with carry of the covered bytes
+pub fn checksum(model: Checksum, pid: u8, data: &[u8]) -> u8 {
+    let init = match model {
+        Checksum::Classic => 0,
+        Checksum::Enhanced => u16::from(pid),
+    };
+
+    let sum = data.iter().fold(init, |sum, &byte| {
+        let sum = sum + u16::from(byte);
+        if sum > 0xff {
+            sum - 0xff
+        } else {
+            sum
+        }
+    });
+
+    !(sum as u8)
+}
+
+// Part of the header `read_header` is waiting for
+#[derive(Clone, Copy)]
+enum Header {
+    Break,
+    Sync,
+    Pid,
+}
+
+/// LIN master or slave node
+pub struct Lin<USART> {
+    tx: Tx<USART>,
+    rx: Rx<USART>,
+    timer: MonoTimer,
+    // timer ticks before a wait gives up
+    timeout: u32,
+    header: Header,
+}
+
+impl<USART> Lin<USART> {
+    /// Uses the two halves of a serial interface configured with `Config::lin`
+    ///
+    /// The waits are timed with `timer`.
+    pub fn new(tx: Tx<USART>, rx: Rx<USART>, timer: MonoTimer) -> Self {
+        Lin {
+            tx,
+            rx,
+            timer,
+            timeout: ticks(timer, DEFAULT_TIMEOUT_US),
+            header: Header::Break,
+        }
+    }
+
+    /// Sets how long, in microseconds, to wait for the break, a byte or its echo before giving up
+    /// with `Error::Timeout`
+    ///
+    /// It should cover the longest response time expected from the slaves. The longest wait is
+    /// `u32::MAX` timer ticks, about 59 s at 72 MHz; longer timeouts are cut down to it.
+    pub fn timeout(mut self, timeout_us: u32) -> Self {
+        self.timeout = ticks(self.timer, timeout_us);
+        self
+    }
+
+    pub fn release(self) -> (Tx<USART>, Rx<USART>) {
+        (self.tx, self.rx)
+    }
+
+    fn timed_out(&self, started: Instant) -> bool {
+        started.elapsed() >= self.timeout
+    }
+}
+
+fn ticks(timer: MonoTimer, us: u32) -> u32 {
+    us.saturating_mul(timer.frequency().0 / 1_000_000)
+}
+
+macro_rules! lin {
+    ($($USARTX:ident,)+) => {
+        $(
+            impl Lin<$USARTX> {
+                /// Master: sends the header of frame `id`
+                pub fn send_header(&mut self, id: u8) -> Result<(), Error> {
+                    let pid = protected_id(id).ok_or(Error::Id)?;
+
+                    self.tx.send_break();
+
+                    let started = self.timer.now();
+                    while !self.take_break() {
+                        if self.timed_out(started) {
+                            return Err(Error::Timeout);
+                        }
+                    }
+
+                    self.write_echoed(SYNC)?;
+                    self.write_echoed(pid)
+                }
+
+                /// Master: sends a whole frame, header and response
+                pub fn write_frame(
+                    &mut self,
+                    id: u8,
+                    data: &[u8],
+                    model: Checksum,
+                ) -> Result<(), Error> {
+                    self.send_header(id)?;
+                    self.write_response(id, data, model)
+                }
+
+                /// Master: sends the header of frame `id` and receives the response of a slave
+                ///
+                /// Blocks until `buffer.len()` data bytes and the checksum have been received, or
+                /// until the timeout runs out.
+                pub fn read_frame(
+                    &mut self,
+                    id: u8,
+                    buffer: &mut [u8],
+                    model: Checksum,
+                ) -> Result<(), Error> {
+                    self.send_header(id)?;
+                    self.read_response(id, buffer, model)
+                }
+
+                /// Slave: receives a header and returns its frame identifier
+                ///
+                /// Returns `WouldBlock` until the break, the sync byte and the protected identifier
+                /// have all been received; a new break starts the header over. This doesn't time
+                /// out: it's up to the caller to decide how long to keep polling.
+                pub fn read_header(&mut self) -> nb::Result<u8, Error> {
+                    if self.take_break() {
+                        self.header = Header::Sync;
+                    }
+
+                    loop {
+                        match self.header {
+                            Header::Break => return Err(nb::Error::WouldBlock),
+                            Header::Sync => {
+                                if self.poll_byte()? != SYNC {
+                                    self.header = Header::Break;
+                                    return Err(nb::Error::Other(Error::Sync));
+                                }
+
+                                self.header = Header::Pid;
+                            }
+                            Header::Pid => {
+                                let pid = self.poll_byte()?;
+                                self.header = Header::Break;
+
+                                return parse_protected_id(pid)
+                                    .ok_or(nb::Error::Other(Error::Parity));
+                            }
+                        }
+                    }
+                }
+
+                /// Sends the response to the header of frame `id`
+                pub fn write_response(
+                    &mut self,
+                    id: u8,
+                    data: &[u8],
+                    model: Checksum,
+                ) -> Result<(), Error> {
+                    if data.len() > MAX_DATA_LEN {
+                        return Err(Error::Length);
+                    }
+                    let pid = protected_id(id).ok_or(Error::Id)?;
+
+                    for &byte in data {
+                        self.write_echoed(byte)?;
+                    }
+
+                    self.write_echoed(checksum(model, pid, data))
+                }
+
+                /// Receives the response to the header of frame `id` into `buffer`
+                pub fn read_response(
+                    &mut self,
+                    id: u8,
+                    buffer: &mut [u8],
+                    model: Checksum,
+                ) -> Result<(), Error> {
+                    if buffer.len() > MAX_DATA_LEN {
+                        return Err(Error::Length);
+                    }
+                    let pid = protected_id(id).ok_or(Error::Id)?;
+
+                    for byte in buffer.iter_mut() {
+                        *byte = self.read_byte()?;
+                    }
+
+                    if self.read_byte()? == checksum(model, pid, buffer) {
+                        Ok(())
+                    } else {
+                        Err(Error::Checksum)
+                    }
+                }
+
+                /// Consumes a detected break, if any
+                fn take_break(&mut self) -> bool {
+                    if self.rx.is_lin_break() {
+                        self.rx.clear_lin_break();
+
+                        // the break is also received as a zero byte with a framing error
+                        let _ = Read::<u8>::read(&mut self.rx);
+
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                // Reads a header byte; on an error `read_header` waits for the next break
+                fn poll_byte(&mut self) -> nb::Result<u8, Error> {
+                    match Read::<u8>::read(&mut self.rx) {
+                        Ok(byte) => Ok(byte),
+                        Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+                        Err(nb::Error::Other(error)) => {
+                            self.header = Header::Break;
+                            Err(nb::Error::Other(Error::Serial(error)))
+                        }
+                    }
+                }
+
+                fn read_byte(&mut self) -> Result<u8, Error> {
+                    let started = self.timer.now();
+                    loop {
+                        match Read::<u8>::read(&mut self.rx) {
+                            Ok(byte) => return Ok(byte),
+                            Err(nb::Error::Other(error)) => return Err(Error::Serial(error)),
+                            Err(nb::Error::WouldBlock) => {}
+                        }
+
+                        if self.timed_out(started) {
+                            return Err(Error::Timeout);
+                        }
+                    }
+                }
+
+                fn write_echoed(&mut self, byte: u8) -> Result<(), Error> {
+                    let started = self.timer.now();
+                    while Write::<u8>::write(&mut self.tx, byte).is_err() {
+                        if self.timed_out(started) {
+                            return Err(Error::Timeout);
+                        }
+                    }
+
+                    if self.read_byte()? == byte {
+                        Ok(())
+                    } else {
+                        Err(Error::Bit)
+                    }
+                }
+            }
+        )+
+    }
+}
+
+lin! {
+    USART1,
+    USART2,
+    USART3,
+    UART4,
+    UART5,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, parse_protected_id, protected_id, Checksum};
+
+    #[test]
+    fn protected_ids() {
+        assert_eq!(protected_id(0x00), Some(0x80));
+        assert_eq!(protected_id(0x01), Some(0xc1));
+        assert_eq!(protected_id(0x3c), Some(0x3c));
+        assert_eq!(protected_id(0x3d), Some(0x7d));
+    }
+
+    #[test]
+    fn ids_wider_than_6_bits_are_rejected() {
+        assert_eq!(protected_id(0x40), None);
+        assert_eq!(protected_id(0xff), None);
+    }
+
+    #[test]
+    fn parse() {
+        for id in 0..0x40 {
+            assert_eq!(parse_protected_id(protected_id(id).unwrap()), Some(id));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_parity_errors() {
+        // 0x01 with P0 and P1 cleared
+        assert_eq!(parse_protected_id(0x01), None);
+        // 0x3c with P0 flipped
+        assert_eq!(parse_protected_id(0x7c), None);
+    }
+
+    #[test]
+    fn classic_checksum() {
+        assert_eq!(checksum(Checksum::Classic, 0xc1, &[0x55, 0x93, 0xe5]), 0x31);
+    }
+
+    #[test]
+    fn enhanced_checksum() {
+        assert_eq!(checksum(Checksum::Enhanced, 0xc1, &[0x55, 0x93, 0xe5]), 0x6f);
+    }
+
+    #[test]
+    fn checksum_carry_wraps_around() {
+        // 0xff + 0x01 = 0x100 -> 0x01
+        assert_eq!(checksum(Checksum::Classic, 0x80, &[0xff, 0x01]), 0xfe);
+        // 0x80 + 0xff = 0x17f -> 0x80, + 0x01 = 0x81
+        assert_eq!(checksum(Checksum::Enhanced, 0x80, &[0xff, 0x01]), 0x7e);
+    }
+}
@@ -0,0 +1,301 @@
+//! RS-485 half-duplex serial interface
+//!
+//! `Rs485` drives the driver enable (DE) pin of an RS-485 transceiver, and optionally its active
+//! low receiver enable (/RE) pin, around each transmission: DE is raised before the first byte is
+//! sent and dropped once the transmission complete (TC) flag reports that the last stop bit has
+//! left the wire. While DE is high, /RE is held high so the receiver doesn't see our own echo.
+//!
+//! Transceivers that have /RE tied to DE, or to ground, can use `NoPin` instead.
+
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+use nb;
+use void::Void;
+
+use crate::device::{UART4, USART1, USART2, USART3};
+use crate::dma::{self, signal, Channel, ReadBuffer, RequestChannel, Transfer, R};
+use crate::hal::blocking::delay::DelayUs;
+use crate::hal::digital::OutputPin;
+use crate::hal::serial::{Read, Write};
+
+use super::{Error, Rx, Tx, WriteDma};
+
+/// Placeholder for an unused receiver enable pin
+pub struct NoPin;
+
+impl OutputPin for NoPin {
+    fn set_high(&mut self) {}
+
+    fn set_low(&mut self) {}
+}
+
+/// RS-485 serial interface
+pub struct Rs485<USART, DE, RE, DELAY> {
+    tx: Tx<USART>,
+    rx: Rx<USART>,
+    de: DE,
+    re: RE,
+    delay: DELAY,
+    // microseconds between raising DE and sending the first byte
+    before: u32,
+    // microseconds between the end of the transmission and dropping DE
+    after: u32,
+}
+
+impl<USART, DE, RE, DELAY> Rs485<USART, DE, RE, DELAY>
+where
+    DE: OutputPin,
+    RE: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    /// Wraps the two halves of a serial interface, leaving the transceiver in receive mode
+    ///
+    /// `delay` is used to wait out the turnaround delays, which are zero by default.
+    pub fn new(tx: Tx<USART>, rx: Rx<USART>, mut de: DE, mut re: RE, delay: DELAY) -> Self {
+        de.set_low();
+        re.set_low();
+
+        Rs485 {
+            tx,
+            rx,
+            de,
+            re,
+            delay,
+            before: 0,
+            after: 0,
+        }
+    }
+
+    /// Sets the turnaround delays, in microseconds
+    ///
+    /// `before` is waited between raising DE and sending the first byte, and `after` between the
+    /// end of the transmission and dropping DE.
+    pub fn turnaround_delays(mut self, before: u32, after: u32) -> Self {
+        self.before = before;
+        self.after = after;
+        self
+    }
+
+    /// Releases the serial interface halves, the pins and the delay provider
+    pub fn release(self) -> (Tx<USART>, Rx<USART>, DE, RE, DELAY) {
+        (self.tx, self.rx, self.de, self.re, self.delay)
+    }
+
+    fn drive(&mut self) {
+        self.re.set_high();
+        self.de.set_high();
+
+        if self.before != 0 {
+            self.delay.delay_us(self.before);
+        }
+    }
+
+    fn release_bus(&mut self) {
+        if self.after != 0 {
+            self.delay.delay_us(self.after);
+        }
+
+        self.de.set_low();
+        self.re.set_low();
+    }
+}
+
+impl<USART, DE, RE, DELAY> crate::hal::blocking::serial::Write<u8> for Rs485<USART, DE, RE, DELAY>
+where
+    Tx<USART>: Write<u8, Error = Void>,
+    DE: OutputPin,
+    RE: OutputPin,
+    DELAY: DelayUs<u32>,
+{
+    type Error = Void;
+
+    /// Sends `buffer` with DE raised, returning once the transceiver is back in receive mode
+    fn bwrite_all(&mut self, buffer: &[u8]) -> Result<(), Void> {
+        self.drive();
+
+        for &byte in buffer {
+            nb::block!(self.tx.write(byte))?;
+        }
+        self.bflush()?;
+
+        self.release_bus();
+        Ok(())
+    }
+
+    fn bflush(&mut self) -> Result<(), Void> {
+        nb::block!(self.tx.flush())
+    }
+}
+
+impl<USART, DE, RE, DELAY> Read<u8> for Rs485<USART, DE, RE, DELAY>
+where
+    Rx<USART>: Read<u8, Error = Error>,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        self.rx.read()
+    }
+}
+
+/// DMA transmission on an RS-485 interface
+///
+/// DE stays raised until `wait` has seen the end of the transmission. Dropping the transfer stops
+/// the DMA and drops DE and /RE right away, cutting the transmission short.
+pub struct Rs485Transfer<BUFFER, CHANNEL, USART, DE, RE, DELAY>
+where
+    CHANNEL: Channel,
+    DE: OutputPin,
+    RE: OutputPin,
+{
+    // stopped before the pins are dropped, see `Drop`
+    transfer: ManuallyDrop<Transfer<R, BUFFER, CHANNEL, Tx<USART>>>,
+    rx: Rx<USART>,
+    de: DE,
+    re: RE,
+    delay: DELAY,
+    before: u32,
+    after: u32,
+}
+
+impl<BUFFER, CHANNEL, USART, DE, RE, DELAY> Rs485Transfer<BUFFER, CHANNEL, USART, DE, RE, DELAY>
+where
+    CHANNEL: Channel,
+    DE: OutputPin,
+    RE: OutputPin,
+{
+    fn into_parts(
+        self,
+    ) -> (
+        Transfer<R, BUFFER, CHANNEL, Tx<USART>>,
+        Rs485Parts<USART, DE, RE, DELAY>,
+    ) {
+        let this = ManuallyDrop::new(self);
+
+        // NOTE(unsafe) each field is read exactly once and `this` is never dropped
+        unsafe {
+            (
+                ManuallyDrop::into_inner(ptr::read(&this.transfer)),
+                (
+                    ptr::read(&this.rx),
+                    ptr::read(&this.de),
+                    ptr::read(&this.re),
+                    ptr::read(&this.delay),
+                    this.before,
+                    this.after,
+                ),
+            )
+        }
+    }
+}
+
+impl<BUFFER, CHANNEL, USART, DE, RE, DELAY> Drop
+    for Rs485Transfer<BUFFER, CHANNEL, USART, DE, RE, DELAY>
+where
+    CHANNEL: Channel,
+    DE: OutputPin,
+    RE: OutputPin,
+{
+    fn drop(&mut self) {
+        // NOTE(unsafe) `transfer` isn't used after this
+        unsafe { ManuallyDrop::drop(&mut self.transfer) }
+
+        self.de.set_low();
+        self.re.set_low();
+    }
+}
+
+// The parts of an `Rs485` other than `tx`, which the transfer owns
+type Rs485Parts<USART, DE, RE, DELAY> = (Rx<USART>, DE, RE, DELAY, u32, u32);
+
+macro_rules! rs485 {
+    ($($USARTX:ident,)+) => {
+        $(
+            impl<DE, RE, DELAY> Rs485<$USARTX, DE, RE, DELAY>
+            where
+                DE: OutputPin,
+                RE: OutputPin,
+                DELAY: DelayUs<u32>,
+            {
+                /// Raises DE and sends `buffer` using DMA
+                pub fn write_all<B>(
+                    mut self,
+                    chan: RequestChannel<$USARTX, signal::Tx>,
+                    buffer: B,
+                ) -> Rs485Transfer<B, RequestChannel<$USARTX, signal::Tx>, $USARTX, DE, RE, DELAY>
+                where
+                    B: ReadBuffer<Word = u8>,
+                {
+                    self.drive();
+
+                    let Rs485 { tx, rx, de, re, delay, before, after } = self;
+
+                    Rs485Transfer {
+                        transfer: ManuallyDrop::new(tx.write_all(chan, buffer)),
+                        rx,
+                        de,
+                        re,
+                        delay,
+                        before,
+                        after,
+                    }
+                }
+            }
+
+            impl<B, DE, RE, DELAY>
+                Rs485Transfer<B, RequestChannel<$USARTX, signal::Tx>, $USARTX, DE, RE, DELAY>
+            where
+                DE: OutputPin,
+                RE: OutputPin,
+                DELAY: DelayUs<u32>,
+            {
+                /// Waits until the last byte has been sent and the transceiver is back in receive
+                /// mode
+                ///
+                /// On a DMA error DE is dropped right away, without the turnaround delay, and the
+                /// buffer, the channel and the interface are returned along with the error.
+                pub fn wait(
+                    self,
+                ) -> Result<
+                    (B, RequestChannel<$USARTX, signal::Tx>, Rs485<$USARTX, DE, RE, DELAY>),
+                    (
+                        dma::Error,
+                        B,
+                        RequestChannel<$USARTX, signal::Tx>,
+                        Rs485<$USARTX, DE, RE, DELAY>,
+                    ),
+                > {
+                    let (transfer, (rx, de, re, delay, before, after)) = self.into_parts();
+
+                    match transfer.wait() {
+                        Ok((buffer, chan, tx)) => {
+                            let mut rs485 = Rs485 { tx, rx, de, re, delay, before, after };
+
+                            // the DMA is done once the last byte has been moved into DR; TC
+                            // tells when it has been shifted out
+                            let _ = crate::hal::blocking::serial::Write::bflush(&mut rs485);
+                            rs485.release_bus();
+
+                            Ok((buffer, chan, rs485))
+                        }
+                        Err((error, buffer, chan, tx)) => {
+                            let mut rs485 = Rs485 { tx, rx, de, re, delay, before, after };
+                            rs485.de.set_low();
+                            rs485.re.set_low();
+
+                            Err((error, buffer, chan, rs485))
+                        }
+                    }
+                }
+            }
+        )+
+    }
+}
+
+rs485! {
+    USART1,
+    USART2,
+    USART3,
+    UART4,
+}
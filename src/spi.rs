@@ -5,6 +5,10 @@ use nb;
 use crate::device::{SPI1, SPI2};
 
 use crate::afio::MAPR;
+use crate::dma::{
+    signal, Channel, CircWriteBuffer, ReadBuffer, Request, RequestChannel, Transfer,
+    TransferPayload, WriteBuffer, R, W,
+};
 use crate::gpio::gpioa::{PA5, PA6, PA7};
 use crate::gpio::gpiob::{PB13, PB14, PB15, PB3, PB4, PB5};
 use crate::gpio::{Alternate, Floating, Input, PushPull};
@@ -24,6 +28,19 @@ pub enum Error {
     _Extensible,
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl crate::hal1::spi::Error for Error {
+    fn kind(&self) -> crate::hal1::spi::ErrorKind {
+        use crate::hal1::spi::ErrorKind;
+
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            Error::ModeFault => ErrorKind::ModeFault,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
 pub trait Pins<SPI> {
     const REMAP: bool;
 }
@@ -77,7 +94,7 @@ impl<PINS> Spi<SPI1, PINS> {
         F: Into<Hertz>,
         PINS: Pins<SPI1>,
     {
-        mapr.mapr().modify(|_, w| w.spi1_remap().bit(PINS::REMAP));
+        mapr.modify_mapr(|_, w| w.spi1_remap().bit(PINS::REMAP));
         Spi::_spi1(spi, pins, mode, freq.into(), clocks, apb)
     }
 }
@@ -214,6 +231,63 @@ macro_rules! hal {
 
             }
 
+            #[cfg(feature = "embedded-hal-1")]
+            impl<PINS> Spi<$SPIX, PINS> {
+                fn exchange(&mut self, byte: u8) -> Result<u8, Error> {
+                    nb::block!(crate::hal::spi::FullDuplex::send(self, byte))?;
+                    nb::block!(crate::hal::spi::FullDuplex::read(self))
+                }
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<PINS> crate::hal1::spi::ErrorType for Spi<$SPIX, PINS> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-hal-1")]
+            impl<PINS> crate::hal1::spi::SpiBus<u8> for Spi<$SPIX, PINS> {
+                fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange(0)?;
+                    }
+
+                    Ok(())
+                }
+
+                fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                    for word in words {
+                        self.exchange(*word)?;
+                    }
+
+                    Ok(())
+                }
+
+                fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+                    for i in 0..read.len().max(write.len()) {
+                        let word = self.exchange(write.get(i).cloned().unwrap_or(0))?;
+                        if let Some(slot) = read.get_mut(i) {
+                            *slot = word;
+                        }
+                    }
+
+                    Ok(())
+                }
+
+                fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange(*word)?;
+                    }
+
+                    Ok(())
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    while self.spi.sr.read().bsy().bit_is_set() {}
+
+                    Ok(())
+                }
+            }
+
             impl<PINS> crate::hal::blocking::spi::transfer::Default<u8> for Spi<$SPIX, PINS> {}
 
             impl<PINS> crate::hal::blocking::spi::write::Default<u8> for Spi<$SPIX, PINS> {}
@@ -225,3 +299,287 @@ hal! {
     SPI1: (_spi1, spi1en, spi1rst, APB2),
     SPI2: (_spi2, spi2en, spi2rst, APB1),
 }
+
+/// Source of the bytes sent while a DMA read is in progress
+static DMA_TX_FILL: u8 = 0;
+
+/// DMA channels serving the requests of a SPI peripheral
+pub trait DmaChannels {
+    /// Channel serving the RX requests
+    type Rx: Channel;
+    /// Channel serving the TX requests
+    type Tx: Channel;
+}
+
+pub trait ReadDma<B>: DmaChannels
+where
+    B: WriteBuffer<Word = u8>,
+    Self: core::marker::Sized,
+{
+    /// Fills `buffer` with the bytes received while sending zeros
+    fn read_exact(
+        self,
+        chans: (Self::Rx, Self::Tx),
+        buffer: B,
+    ) -> Transfer<W, B, Self::Rx, (Self, Self::Tx)>;
+}
+
+pub trait WriteDma<B>: DmaChannels
+where
+    B: ReadBuffer<Word = u8>,
+    Self: core::marker::Sized,
+{
+    /// Sends `buffer` discarding the received bytes
+    fn write_all(
+        self,
+        chans: (Self::Rx, Self::Tx),
+        buffer: B,
+    ) -> Transfer<R, B, Self::Rx, (Self, Self::Tx)>;
+}
+
+pub trait CircWriteDma<B>: DmaChannels
+where
+    B: AsMut<[u8]>,
+    Self: core::marker::Sized,
+{
+    /// Keeps sending the two halves of `buffer`, which should be filled beforehand, discarding
+    /// the received bytes
+    ///
+    /// `CircWriteBuffer::stop` gives back the SPI and the RX channel.
+    fn circ_write(
+        self,
+        chans: (Self::Rx, Self::Tx),
+        buffer: &'static mut [B; 2],
+    ) -> CircWriteBuffer<B, Self::Tx, (Self, Self::Rx)>;
+}
+
+pub trait ReadWriteDma<B>: DmaChannels
+where
+    B: WriteBuffer<Word = u8>,
+    Self: core::marker::Sized,
+{
+    /// Sends `buffer` replacing its contents with the received bytes (full duplex)
+    fn read_write(
+        self,
+        chans: (Self::Rx, Self::Tx),
+        buffer: B,
+    ) -> Transfer<W, B, Self::Rx, (Self, Self::Tx)>;
+}
+
+macro_rules! dma {
+    ($($SPIX:ident: $SINK:ident,)+) => {
+        $(
+            /// Sink for the bytes received while a DMA write is in progress
+            static mut $SINK: u8 = 0;
+
+            impl<PINS> DmaChannels for Spi<$SPIX, PINS> {
+                type Rx = RequestChannel<$SPIX, signal::Rx>;
+                type Tx = RequestChannel<$SPIX, signal::Tx>;
+            }
+
+            impl<PINS> Spi<$SPIX, PINS> {
+                /// Moves `len` bytes through both DMA channels
+                ///
+                /// The transfer is complete once the RX channel is done.
+                fn start_dma(
+                    &mut self,
+                    rx: &mut RequestChannel<$SPIX, signal::Rx>,
+                    tx: &mut RequestChannel<$SPIX, signal::Tx>,
+                    (rx_address, rx_minc): (u32, bool),
+                    (tx_address, tx_minc): (u32, bool),
+                    len: usize,
+                ) {
+                    // the TX channel may still be enabled from an earlier use
+                    tx.stop();
+
+                    rx.start_request::<$SPIX, signal::Rx, u8>(rx_address, len, false, rx_minc, false);
+                    tx.start_request::<$SPIX, signal::Tx, u8>(tx_address, len, true, tx_minc, false);
+
+                    // the RX requests must be enabled before the first byte is sent
+                    self.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+                    self.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+                }
+            }
+
+            impl<B, PINS> ReadDma<B> for Spi<$SPIX, PINS> where B: WriteBuffer<Word = u8> {
+                fn read_exact(
+                    mut self,
+                    (mut rx, mut tx): (Self::Rx, Self::Tx),
+                    mut buffer: B,
+                ) -> Transfer<W, B, Self::Rx, (Self, Self::Tx)> {
+                    // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                    let (ptr, len) = unsafe { buffer.write_buffer() };
+                    self.start_dma(
+                        &mut rx,
+                        &mut tx,
+                        (ptr as usize as u32, true),
+                        (&DMA_TX_FILL as *const u8 as usize as u32, false),
+                        len,
+                    );
+
+                    Transfer::w(buffer, rx, (self, tx), len)
+                }
+            }
+
+            impl<B, PINS> WriteDma<B> for Spi<$SPIX, PINS> where B: ReadBuffer<Word = u8> {
+                fn write_all(
+                    mut self,
+                    (mut rx, mut tx): (Self::Rx, Self::Tx),
+                    buffer: B,
+                ) -> Transfer<R, B, Self::Rx, (Self, Self::Tx)> {
+                    // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                    let (ptr, len) = unsafe { buffer.read_buffer() };
+                    // NOTE(unsafe) only the address is taken; no reference to the sink is ever
+                    // created, it's only written by the DMA and never read
+                    let sink = unsafe { ptr::addr_of_mut!($SINK) as usize as u32 };
+                    self.start_dma(
+                        &mut rx,
+                        &mut tx,
+                        (sink, false),
+                        (ptr as usize as u32, true),
+                        len,
+                    );
+
+                    Transfer::r(buffer, rx, (self, tx), len)
+                }
+            }
+
+            impl<B, PINS> CircWriteDma<B> for Spi<$SPIX, PINS> where B: AsMut<[u8]> {
+                fn circ_write(
+                    self,
+                    (mut rx, mut tx): (Self::Rx, Self::Tx),
+                    buffer: &'static mut [B; 2],
+                ) -> CircWriteBuffer<B, Self::Tx, (Self, Self::Rx)> {
+                    let len = buffer[0].as_mut().len() * 2;
+
+                    // the TX channel may still be enabled from an earlier use
+                    tx.stop();
+
+                    // NOTE(unsafe) only the address is taken; no reference to the sink is ever
+                    // created, it's only written by the DMA and never read
+                    let sink = unsafe { ptr::addr_of_mut!($SINK) as usize as u32 };
+                    rx.start_request::<$SPIX, signal::Rx, u8>(sink, len, false, false, true);
+                    self.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+
+                    let buffer =
+                        tx.circ_write_request::<$SPIX, signal::Tx, B, u8>(Request::new(), buffer);
+                    self.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+                    buffer.with_payload((self, rx))
+                }
+            }
+
+            // the SPI paired with the DMA channel that isn't tracked by the transfer
+            impl<PINS, CHANNEL> TransferPayload for (Spi<$SPIX, PINS>, CHANNEL)
+            where
+                CHANNEL: Channel,
+            {
+                fn stop(&mut self) {
+                    let (spi, chan) = self;
+
+                    chan.stop();
+                    spi.spi
+                        .cr2
+                        .modify(|_, w| w.rxdmaen().clear_bit().txdmaen().clear_bit());
+                }
+            }
+
+            impl<B, PINS> ReadWriteDma<B> for Spi<$SPIX, PINS> where B: WriteBuffer<Word = u8> {
+                fn read_write(
+                    mut self,
+                    (mut rx, mut tx): (Self::Rx, Self::Tx),
+                    mut buffer: B,
+                ) -> Transfer<W, B, Self::Rx, (Self, Self::Tx)> {
+                    // NOTE(unsafe) the buffer is owned by the transfer until it's done
+                    let (ptr, len) = unsafe { buffer.write_buffer() };
+                    let address = ptr as usize as u32;
+                    self.start_dma(&mut rx, &mut tx, (address, true), (address, true), len);
+
+                    Transfer::w(buffer, rx, (self, tx), len)
+                }
+            }
+        )+
+    }
+}
+
+dma! {
+    SPI1: SPI1_RX_SINK,
+    SPI2: SPI2_RX_SINK,
+}
+
+/// SPI bus with a dedicated chip select pin
+///
+/// Implements the `embedded-hal` 1.0 `SpiDevice` trait: CS is asserted (driven low) for the whole
+/// duration of a transaction.
+#[cfg(feature = "embedded-hal-1")]
+pub struct ExclusiveDevice<SPI, PINS, CS> {
+    spi: Spi<SPI, PINS>,
+    cs: CS,
+    sysclk: Hertz,
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<SPI, PINS, CS> ExclusiveDevice<SPI, PINS, CS>
+where
+    CS: crate::hal1::digital::OutputPin<Error = core::convert::Infallible>,
+{
+    /// Wraps `spi` and the chip select pin `cs`, which is driven high (deasserted)
+    pub fn new(spi: Spi<SPI, PINS>, mut cs: CS, clocks: Clocks) -> Self {
+        let _ = cs.set_high();
+
+        ExclusiveDevice {
+            spi,
+            cs,
+            sysclk: clocks.sysclk(),
+        }
+    }
+
+    /// Releases the SPI bus and the chip select pin
+    pub fn free(self) -> (Spi<SPI, PINS>, CS) {
+        (self.spi, self.cs)
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<SPI, PINS, CS> crate::hal1::spi::ErrorType for ExclusiveDevice<SPI, PINS, CS> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<SPI, PINS, CS> crate::hal1::spi::SpiDevice<u8> for ExclusiveDevice<SPI, PINS, CS>
+where
+    Spi<SPI, PINS>: crate::hal1::spi::SpiBus<u8, Error = Error>,
+    CS: crate::hal1::digital::OutputPin<Error = core::convert::Infallible>,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [crate::hal1::spi::Operation<'_, u8>],
+    ) -> Result<(), Error> {
+        use crate::hal1::spi::{Operation, SpiBus};
+
+        let _ = self.cs.set_low();
+
+        let mut result = Ok(());
+        for operation in operations {
+            result = match operation {
+                Operation::Read(words) => self.spi.read(words),
+                Operation::Write(words) => self.spi.write(words),
+                Operation::Transfer(read, write) => self.spi.transfer(read, write),
+                Operation::TransferInPlace(words) => self.spi.transfer_in_place(words),
+                Operation::DelayNs(ns) => self.spi.flush().map(|_| {
+                    let cycles = u64::from(*ns) * u64::from(self.sysclk.0) / 1_000_000_000;
+                    cortex_m::asm::delay(cycles as u32);
+                }),
+            };
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        let flushed = self.spi.flush();
+        let _ = self.cs.set_high();
+
+        result.and(flushed)
+    }
+}